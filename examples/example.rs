@@ -1,7 +1,5 @@
 #![allow(unused_variables)]
 
-use std::path::Path;
-
 use mini_fs::prelude::*;
 use mini_fs::{LocalFs, MiniFs, TarFs};
 
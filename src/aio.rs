@@ -0,0 +1,347 @@
+//! This module contains async versions of the store traits.
+//!
+//! [`AsyncStore`] is implemented for [`LocalFs`] (using `tokio::fs`) and
+//! [`RamFs`]. Any other [`Store`] can be used asynchronously by wrapping it in a
+//! [`Blocking`] adapter, which runs it on tokio's blocking thread pool.
+//!
+//! The async traits use the same method names as the blocking ones, so avoid
+//! importing both in the same scope.
+//!
+//! Requires the `tokio` feature.
+//!
+//! [`AsyncStore`]: ./trait.AsyncStore.html
+//! [`Blocking`]: ./struct.Blocking.html
+//! [`LocalFs`]: ../struct.LocalFs.html
+//! [`RamFs`]: ../struct.RamFs.html
+//! [`Store`]: ../trait.Store.html
+use std::future::{self, Future};
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio_::fs::{self, DirEntry, ReadDir};
+use tokio_::task;
+
+use crate::index::normalize_path;
+use crate::store::Store;
+use crate::{Entry, EntryKind, LocalFs, Metadata, RamFs};
+
+/// Boxed future returned by the methods of [`AsyncStore`].
+///
+/// [`AsyncStore`]: ./trait.AsyncStore.html
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Stream of file entries.
+///
+/// Unlike [`Entries`], the stream doesn't borrow from the store.
+///
+/// [`Entries`]: ../struct.Entries.html
+pub struct AsyncEntries {
+    inner: Pin<Box<dyn Stream<Item = io::Result<Entry>> + Send>>,
+}
+
+impl AsyncEntries {
+    pub fn new<S>(stream: S) -> Self
+    where
+        S: Stream<Item = io::Result<Entry>> + Send + 'static,
+    {
+        Self {
+            inner: Box::pin(stream),
+        }
+    }
+
+    /// Stream of entries that are already available.
+    pub fn ready<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = io::Result<Entry>>,
+        <I as IntoIterator>::IntoIter: Send + 'static,
+    {
+        Self::new(Iter(Box::new(iter.into_iter())))
+    }
+}
+
+impl Stream for AsyncEntries {
+    type Item = io::Result<Entry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+struct Iter(Box<dyn Iterator<Item = io::Result<Entry>> + Send>);
+
+impl Stream for Iter {
+    type Item = io::Result<Entry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.0.next())
+    }
+}
+
+/// Generic async file storage.
+pub trait AsyncStore: Send + Sync {
+    type File: Send;
+
+    fn open_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<Self::File>>;
+
+    /// Returns a stream over the files & directory entries in a given path.
+    fn entries_path<'a>(&'a self, _: &'a Path) -> BoxFuture<'a, io::Result<AsyncEntries>> {
+        Box::pin(future::ready(Err(io::Error::other(
+            "entries_path is not implemented.",
+        ))))
+    }
+
+    /// Returns the metadata of the file or directory in a given path.
+    fn metadata_path<'a>(&'a self, _: &'a Path) -> BoxFuture<'a, io::Result<Metadata>> {
+        Box::pin(future::ready(Err(io::Error::other(
+            "metadata_path is not implemented.",
+        ))))
+    }
+
+    /// Returns the target of the symbolic link in a given path.
+    fn read_link_path<'a>(&'a self, _: &'a Path) -> BoxFuture<'a, io::Result<PathBuf>> {
+        Box::pin(future::ready(Err(io::Error::other(
+            "read_link_path is not implemented.",
+        ))))
+    }
+}
+
+/// Convenient methods on top of [`AsyncStore`].
+///
+/// [`AsyncStore`]: ./trait.AsyncStore.html
+pub trait AsyncStoreExt: AsyncStore {
+    fn entries<P: AsRef<Path>>(&self, path: P) -> BoxFuture<'_, io::Result<AsyncEntries>> {
+        let path = normalize_path(path.as_ref()).into_owned();
+        Box::pin(async move { self.entries_path(&path).await })
+    }
+
+    fn open<P: AsRef<Path>>(&self, path: P) -> BoxFuture<'_, io::Result<Self::File>> {
+        let path = normalize_path(path.as_ref()).into_owned();
+        Box::pin(async move { self.open_path(&path).await })
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> BoxFuture<'_, io::Result<Metadata>> {
+        let path = normalize_path(path.as_ref()).into_owned();
+        Box::pin(async move { self.metadata_path(&path).await })
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> BoxFuture<'_, io::Result<PathBuf>> {
+        let path = normalize_path(path.as_ref()).into_owned();
+        Box::pin(async move { self.read_link_path(&path).await })
+    }
+}
+
+impl<T: AsyncStore> AsyncStoreExt for T {}
+
+impl AsyncStore for LocalFs {
+    type File = fs::File;
+
+    fn open_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<fs::File>> {
+        Box::pin(async move {
+            let path = resolve(self, path).await?;
+            if !self.follow_links && fs::symlink_metadata(&path).await?.file_type().is_symlink() {
+                return Err(io::Error::other("Is a symbolic link."));
+            }
+            fs::File::open(path).await
+        })
+    }
+
+    fn entries_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<AsyncEntries>> {
+        Box::pin(async move {
+            let read_dir = fs::read_dir(resolve(self, path).await?).await?;
+            let ctx = (self.root.clone(), self.follow_links);
+            Ok(AsyncEntries::new(LocalEntries {
+                next: Some(next_local_entry(read_dir, ctx)),
+            }))
+        })
+    }
+
+    fn metadata_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<Metadata>> {
+        Box::pin(async move {
+            let path = resolve(self, path).await?;
+            let meta = if self.follow_links {
+                fs::metadata(path).await?
+            } else {
+                fs::symlink_metadata(path).await?
+            };
+            Ok(crate::local_metadata(&meta))
+        })
+    }
+
+    fn read_link_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<PathBuf>> {
+        Box::pin(async move { fs::read_link(resolve(self, path).await?).await })
+    }
+}
+
+// Resolving sandboxed paths needs to follow symlinks, which blocks.
+async fn resolve(local: &LocalFs, path: &Path) -> io::Result<PathBuf> {
+    if !local.sandboxed {
+        return Ok(local.root.join(path));
+    }
+    let root = local.root.clone();
+    let path = path.to_path_buf();
+    task::spawn_blocking(move || crate::sandbox_path(&root, &path))
+        .await
+        .map_err(io::Error::other)?
+}
+
+// Root of the store, and whether links are followed.
+type LocalContext = (PathBuf, bool);
+
+type LocalEntry = Option<(io::Result<Entry>, ReadDir, LocalContext)>;
+
+// Entries of a local directory, read one at a time.
+struct LocalEntries {
+    next: Option<BoxFuture<'static, LocalEntry>>,
+}
+
+fn next_local_entry(mut read_dir: ReadDir, ctx: LocalContext) -> BoxFuture<'static, LocalEntry> {
+    Box::pin(async move {
+        let entry = match read_dir.next_entry().await {
+            Ok(Some(entry)) => local_entry(&ctx, entry).await,
+            Ok(None) => return None,
+            Err(e) => Err(e),
+        };
+        Some((entry, read_dir, ctx))
+    })
+}
+
+async fn local_entry(
+    &(ref root, follow_links): &LocalContext,
+    entry: DirEntry,
+) -> io::Result<Entry> {
+    let path = entry
+        .path()
+        .strip_prefix(root)
+        .map(Path::to_path_buf)
+        .expect("Error striping path suffix.");
+    let mut kind = crate::local_kind(entry.file_type().await?);
+    // dangling links are listed as links.
+    if kind == EntryKind::Symlink && follow_links {
+        if let Ok(meta) = fs::metadata(entry.path()).await {
+            kind = crate::local_kind(meta.file_type());
+        }
+    }
+    Ok(Entry {
+        name: path.into_os_string(),
+        kind,
+    })
+}
+
+impl Stream for LocalEntries {
+    type Item = io::Result<Entry>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let next = match self.next {
+            Some(ref mut next) => next.as_mut().poll(cx),
+            None => return Poll::Ready(None),
+        };
+        match next {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Some((entry, read_dir, ctx))) => {
+                self.next = Some(next_local_entry(read_dir, ctx));
+                Poll::Ready(Some(entry))
+            }
+            Poll::Ready(None) => {
+                self.next = None;
+                Poll::Ready(None)
+            }
+        }
+    }
+}
+
+// The files are in memory, so none of these operations block.
+impl AsyncStore for RamFs {
+    type File = Cursor<Arc<[u8]>>;
+
+    fn open_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<Self::File>> {
+        let file = crate::ram_file(&crate::read_index(&self.index), path);
+        Box::pin(future::ready(file.map(Cursor::new)))
+    }
+
+    fn entries_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<AsyncEntries>> {
+        let entries = Store::entries_path(self, path).map(|e| e.collect::<Vec<_>>());
+        Box::pin(future::ready(entries.map(AsyncEntries::ready)))
+    }
+
+    fn metadata_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<Metadata>> {
+        Box::pin(future::ready(Store::metadata_path(self, path)))
+    }
+
+    fn read_link_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<PathBuf>> {
+        Box::pin(future::ready(Store::read_link_path(self, path)))
+    }
+}
+
+/// Adapter to use a blocking [`Store`] asynchronously.
+///
+/// Every operation runs on tokio's blocking thread pool, so it must be used
+/// from within a tokio runtime. Files are read into memory before they are
+/// returned.
+///
+/// [`Store`]: ../trait.Store.html
+pub struct Blocking<S> {
+    store: Arc<S>,
+}
+
+impl<S> Blocking<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+        }
+    }
+}
+
+impl<S> Blocking<S>
+where
+    S: Store + Send + Sync + 'static,
+{
+    // Runs the closure on the blocking thread pool.
+    fn run<T, F>(&self, path: &Path, f: F) -> BoxFuture<'static, io::Result<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(&S, &Path) -> io::Result<T> + Send + 'static,
+    {
+        let store = Arc::clone(&self.store);
+        let path = path.to_path_buf();
+        Box::pin(async move {
+            task::spawn_blocking(move || f(&store, &path))
+                .await
+                .map_err(io::Error::other)?
+        })
+    }
+}
+
+impl<S> AsyncStore for Blocking<S>
+where
+    S: Store + Send + Sync + 'static,
+    S::File: Read,
+{
+    type File = Cursor<Vec<u8>>;
+
+    fn open_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<Self::File>> {
+        self.run(path, |store, path| {
+            let mut data = Vec::new();
+            store.open_path(path)?.read_to_end(&mut data)?;
+            Ok(Cursor::new(data))
+        })
+    }
+
+    fn entries_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<AsyncEntries>> {
+        let entries = self.run(path, |store, path| {
+            Ok(store.entries_path(path)?.collect::<Vec<_>>())
+        });
+        Box::pin(async move { entries.await.map(AsyncEntries::ready) })
+    }
+
+    fn metadata_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<Metadata>> {
+        self.run(path, |store, path| store.metadata_path(path))
+    }
+
+    fn read_link_path<'a>(&'a self, path: &'a Path) -> BoxFuture<'a, io::Result<PathBuf>> {
+        self.run(path, |store, path| store.read_link_path(path))
+    }
+}
@@ -21,7 +21,7 @@ use std::path::{Component, Path, PathBuf};
 
 use crate::index::normalize_path;
 use crate::prelude::*;
-use crate::store::Entries;
+use crate::store::{Entries, Metadata};
 
 /// Caseless filesystem wrapping an inner filesystem.
 #[derive(Clone, Debug)]
@@ -64,7 +64,7 @@ impl<S: Store> CaselessFs<S> {
         let mut paths = vec![PathBuf::new()];
         for component in path.components() {
             paths = find_next_ascii_lowercase(&self.inner, &component, paths);
-            if paths.len() == 0 {
+            if paths.is_empty() {
                 return paths;
             }
         }
@@ -85,16 +85,50 @@ impl<S: Store> Store for CaselessFs<S> {
             return Ok(file);
         }
         // caseless path
-        for path in self.find(path) {
-            return self.inner.open_path(&path);
+        match self.find(path).first() {
+            Some(path) => self.inner.open_path(path),
+            None => Err(io::ErrorKind::NotFound.into()),
         }
-        Err(io::ErrorKind::NotFound.into())
     }
 
     /// Iterates over the entries of the inner filesystem.
-    fn entries_path(&self, path: &Path) -> io::Result<Entries> {
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
         self.inner.entries_path(path)
     }
+
+    /// Returns the metadata of the file or directory identified by the
+    /// caseless path.
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        // real path
+        if let Ok(meta) = self.inner.metadata_path(path) {
+            return Ok(meta);
+        }
+        // caseless path
+        match self.find(path).first() {
+            Some(path) => self.inner.metadata_path(path),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    /// Reads the symbolic link identified by the caseless path.
+    fn read_link_path(&self, path: &Path) -> io::Result<PathBuf> {
+        // real path
+        if let Ok(target) = self.inner.read_link_path(path) {
+            return Ok(target);
+        }
+        // caseless path
+        match self.find(path).first() {
+            Some(path) => self.inner.read_link_path(path),
+            None => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    /// Paths are case-insensitive, so glob patterns are matched in a
+    /// case-insensitive way. The matched paths are the real paths of the inner
+    /// filesystem.
+    fn case_sensitive_path(&self, _: &Path) -> bool {
+        false
+    }
 }
 
 /// Finds the next path candidates.
@@ -112,21 +146,19 @@ fn find_next_ascii_lowercase<S: Store>(
             return next;
         }
         _ => {
-            panic!(format!("unexpected path component {:?}", component));
+            panic!("unexpected path component {:?}", component);
         }
     };
     if let Some(t_s) = target.to_str() {
         // compare utf8
         for path in paths {
             if let Ok(entries) = fs.entries(&path) {
-                for e in entries {
-                    if let Ok(entry) = e {
-                        if let Some(e_s) = entry.name.to_str() {
-                            if t_s.to_ascii_lowercase() == e_s.to_ascii_lowercase() {
-                                let mut path = path.to_owned();
-                                path.push(&entry.name);
-                                next.push(path);
-                            }
+                for entry in entries.flatten() {
+                    if let Some(e_s) = entry.name.to_str() {
+                        if t_s.eq_ignore_ascii_case(e_s) {
+                            let mut path = path.to_owned();
+                            path.push(&entry.name);
+                            next.push(path);
                         }
                     }
                 }
@@ -136,13 +168,11 @@ fn find_next_ascii_lowercase<S: Store>(
         // compare raw
         for path in paths {
             if let Ok(entries) = fs.entries(&path) {
-                for e in entries {
-                    if let Ok(entry) = e {
-                        if &entry.name == &target {
-                            let mut path = path.to_owned();
-                            path.push(&entry.name);
-                            next.push(path);
-                        }
+                for entry in entries.flatten() {
+                    if entry.name == target {
+                        let mut path = path.to_owned();
+                        path.push(&entry.name);
+                        next.push(path);
                     }
                 }
             }
@@ -0,0 +1,437 @@
+//! This module contains glob pattern matching over the paths of a store.
+//!
+//! Patterns are matched one path component at a time and support:
+//!
+//! - `?` matches any single character.
+//! - `*` matches any sequence of characters, including names that start with a
+//!   dot.
+//! - `**` as a whole component matches any number of directories. At the end
+//!   of a pattern, it matches everything below the directory.
+//! - `[abc]`, `[a-z]` match one of the characters of the class, and `[!abc]`
+//!   (or `[^abc]`) one character that isn't.
+//! - `{a,b}` matches either of the alternatives, which can be nested.
+//! - `\` escapes the next character.
+//!
+//! Only the directories that the pattern can match are listed, so a pattern
+//! like `/assets/**/*.png` doesn't walk the directories outside of `/assets`.
+//!
+//! ```
+//! use mini_fs::prelude::*;
+//! use mini_fs::RamFs;
+//!
+//! let mut ram = RamFs::new();
+//! ram.touch("gfx/a.png", vec![]);
+//! ram.touch("gfx/ui/b.png", vec![]);
+//! ram.touch("sfx/c.ogg", vec![]);
+//!
+//! assert_eq!(2, ram.glob("**/*.png")?.count());
+//! assert_eq!(3, ram.glob("{gfx,sfx}/**/*.{png,ogg}")?.count());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! Names are matched in a case-insensitive way in the directories of a
+//! [`CaselessFs`], including when it's mounted in a [`MiniFs`] or used as a
+//! layer of an overlay.
+//!
+//! [`CaselessFs`]: ../caseless/struct.CaselessFs.html
+//! [`MiniFs`]: ../struct.MiniFs.html
+use std::collections::BTreeSet;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use crate::store::{EntryKind, Store};
+use crate::walk::Walk;
+
+/// Iterator over the paths of a store that match a glob pattern.
+///
+/// Created with the [`glob`] method of [`StoreExt`]. Paths are returned in the
+/// order they are found, without duplicates.
+///
+/// [`glob`]: ../trait.StoreExt.html#method.glob
+/// [`StoreExt`]: ../trait.StoreExt.html
+pub struct Glob<'a, S: ?Sized> {
+    store: &'a S,
+    // one pattern per brace alternative.
+    patterns: Vec<Vec<Part>>,
+    stack: Vec<State>,
+    // partial matches that have been expanded, so paths reached by more than
+    // one `**` aren't expanded again.
+    expanded: BTreeSet<(usize, usize, PathBuf)>,
+    seen: BTreeSet<PathBuf>,
+    caseless: bool,
+}
+
+// Path matched so far, and the next component of a pattern to match.
+struct State {
+    path: PathBuf,
+    pattern: usize,
+    part: usize,
+    // false if the path hasn't been checked to exist.
+    exists: bool,
+}
+
+// Component of a pattern.
+enum Part {
+    Literal(String),
+    Match(Vec<Token>),
+    // `**`
+    Recursive,
+}
+
+enum Token {
+    Char(char),
+    Any,
+    Star,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl<'a, S: Store + ?Sized> Glob<'a, S> {
+    pub(crate) fn new(store: &'a S, pattern: &str) -> io::Result<Self> {
+        let mut patterns = Vec::new();
+        let mut stack = Vec::new();
+        for pattern in expand_braces(pattern)? {
+            let parts = parse(&pattern)?;
+            if parts.is_empty() {
+                continue;
+            }
+            let root = if pattern.starts_with('/') { "/" } else { "" };
+            stack.push(State {
+                path: PathBuf::from(root),
+                pattern: patterns.len(),
+                part: 0,
+                exists: true,
+            });
+            patterns.push(parts);
+        }
+        // the first alternative is matched first.
+        stack.reverse();
+        Ok(Self {
+            store,
+            patterns,
+            stack,
+            expanded: BTreeSet::new(),
+            seen: BTreeSet::new(),
+            caseless: false,
+        })
+    }
+
+    /// Match names in a case-insensitive way.
+    ///
+    /// Names are always matched this way in the directories where the store
+    /// compares them in a case-insensitive way, like in a [`CaselessFs`].
+    ///
+    /// Literal components of the pattern need their parent directory to be
+    /// listed, instead of being looked up directly.
+    ///
+    /// [`CaselessFs`]: ../caseless/struct.CaselessFs.html
+    pub fn case_insensitive(mut self) -> Self {
+        self.caseless = true;
+        self
+    }
+
+    // Returns the states that follow from matching the next component.
+    fn step(&self, state: &State) -> io::Result<Vec<State>> {
+        let caseless = self.caseless || !self.store.case_sensitive_path(&state.path);
+        let parts = &self.patterns[state.pattern];
+        let last = state.part + 1 == parts.len();
+        let next = |path: PathBuf, exists: bool| State {
+            path,
+            pattern: state.pattern,
+            part: state.part + 1,
+            exists,
+        };
+        match parts[state.part] {
+            Part::Literal(ref name) if !caseless || name == ".." => {
+                let mut path = state.path.clone();
+                if name == ".." {
+                    path.pop();
+                } else {
+                    path.push(name);
+                }
+                Ok(vec![next(path, false)])
+            }
+            Part::Recursive => {
+                let mut states = Vec::new();
+                if !last {
+                    states.push(next(state.path.clone(), state.exists));
+                }
+                for entry in Walk::new(self.store, state.path.clone()) {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(ref err) if no_match(err) => continue,
+                        Err(err) => return Err(err),
+                    };
+                    // a trailing `**` matches everything below the directory.
+                    if last || entry.kind == EntryKind::Dir {
+                        states.push(next(entry.path, true));
+                    }
+                }
+                Ok(states)
+            }
+            ref part => {
+                let entries = match self.store.entries_path(&state.path) {
+                    Ok(entries) => entries,
+                    Err(ref err) if no_match(err) => return Ok(Vec::new()),
+                    Err(err) => return Err(err),
+                };
+                let mut states = Vec::new();
+                for entry in entries {
+                    let entry = entry?;
+                    let name = entry.file_name();
+                    let matches = part.matches(&name.to_string_lossy(), caseless);
+                    if matches && (last || entry.kind == EntryKind::Dir) {
+                        states.push(next(state.path.join(name), true));
+                    }
+                }
+                Ok(states)
+            }
+        }
+    }
+}
+
+impl<S: Store + ?Sized> Iterator for Glob<'_, S> {
+    type Item = io::Result<PathBuf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(state) = self.stack.pop() {
+            if state.part < self.patterns[state.pattern].len() {
+                let key = (state.pattern, state.part, state.path.clone());
+                if !self.expanded.insert(key) {
+                    continue;
+                }
+                match self.step(&state) {
+                    Ok(states) => self.stack.extend(states.into_iter().rev()),
+                    Err(err) => return Some(Err(err)),
+                }
+                continue;
+            }
+            if !state.exists {
+                match self.store.metadata_path(&state.path) {
+                    Ok(_) => {}
+                    Err(ref err)
+                        if matches!(err.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) =>
+                    {
+                        continue
+                    }
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+            if self.seen.insert(state.path.clone()) {
+                return Some(Ok(state.path));
+            }
+        }
+        None
+    }
+}
+
+// Errors of directories that can't be listed: they don't exist, they are
+// files, or the store doesn't list directories.
+fn no_match(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::NotFound | ErrorKind::NotADirectory | ErrorKind::Other
+    )
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidInput, msg)
+}
+
+// Expands the brace alternatives of a pattern into a list of patterns.
+fn expand_braces(pattern: &str) -> io::Result<Vec<String>> {
+    let chars: Vec<char> = pattern.chars().collect();
+    // position of the first top-level brace, its closing brace, and the commas
+    // in between.
+    let mut open = None;
+    let mut commas = Vec::new();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => {
+                if depth == 0 {
+                    open = Some(i);
+                }
+                depth += 1;
+            }
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    let open = match open {
+        Some(open) if depth == 0 => open,
+        Some(_) => return Err(invalid("Unclosed brace in glob pattern.")),
+        None => return Ok(vec![pattern.to_string()]),
+    };
+    let close = i;
+    let prefix: String = chars[..open].iter().collect();
+    let suffix: String = chars[close + 1..].iter().collect();
+    let mut bounds = vec![open];
+    bounds.extend(commas);
+    bounds.push(close);
+
+    let mut patterns = Vec::new();
+    for alt in bounds.windows(2) {
+        let alt: String = chars[alt[0] + 1..alt[1]].iter().collect();
+        patterns.extend(expand_braces(&format!("{}{}{}", prefix, alt, suffix))?);
+    }
+    Ok(patterns)
+}
+
+// Splits a pattern into components.
+fn parse(pattern: &str) -> io::Result<Vec<Part>> {
+    let mut parts = Vec::new();
+    for comp in pattern.split('/') {
+        match comp {
+            "" | "." => {}
+            "**" => parts.push(Part::Recursive),
+            _ => {
+                let tokens = tokenize(comp)?;
+                let literal: Option<String> = tokens
+                    .iter()
+                    .map(|t| match *t {
+                        Token::Char(c) => Some(c),
+                        _ => None,
+                    })
+                    .collect();
+                match literal {
+                    Some(literal) => parts.push(Part::Literal(literal)),
+                    None => parts.push(Part::Match(tokens)),
+                }
+            }
+        }
+    }
+    Ok(parts)
+}
+
+fn tokenize(comp: &str) -> io::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = comp.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '?' => Token::Any,
+            '*' => {
+                while chars.peek() == Some(&'*') {
+                    chars.next();
+                }
+                Token::Star
+            }
+            '\\' => Token::Char(chars.next().unwrap_or('\\')),
+            '[' => {
+                let negated = matches!(chars.peek(), Some('!') | Some('^'));
+                if negated {
+                    chars.next();
+                }
+                let mut ranges = Vec::new();
+                let mut first = true;
+                loop {
+                    let c = match chars.next() {
+                        // a `]` right after the opening bracket is a literal.
+                        Some(']') if !first => break,
+                        Some('\\') => chars.next().unwrap_or('\\'),
+                        Some(c) => c,
+                        None => return Err(invalid("Unclosed character class in glob pattern.")),
+                    };
+                    first = false;
+                    let mut lookahead = chars.clone();
+                    match (lookahead.next(), lookahead.next()) {
+                        (Some('-'), Some(end)) if end != ']' => {
+                            chars.next();
+                            chars.next();
+                            ranges.push((c, end));
+                        }
+                        _ => ranges.push((c, c)),
+                    }
+                }
+                Token::Class { negated, ranges }
+            }
+            c => Token::Char(c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn eq<A, B>(a: A, b: B, caseless: bool) -> bool
+where
+    A: Iterator<Item = char> + Clone,
+    B: Iterator<Item = char> + Clone,
+{
+    if caseless {
+        a.flat_map(char::to_lowercase)
+            .eq(b.flat_map(char::to_lowercase))
+    } else {
+        a.eq(b)
+    }
+}
+
+impl Part {
+    fn matches(&self, name: &str, caseless: bool) -> bool {
+        match *self {
+            Part::Literal(ref literal) => eq(literal.chars(), name.chars(), caseless),
+            Part::Match(ref tokens) => matches(tokens, name, caseless),
+            Part::Recursive => true,
+        }
+    }
+}
+
+impl Token {
+    fn matches(&self, c: char, caseless: bool) -> bool {
+        match *self {
+            Token::Char(t) => eq(Some(t).into_iter(), Some(c).into_iter(), caseless),
+            Token::Any => true,
+            Token::Star => false,
+            Token::Class {
+                negated,
+                ref ranges,
+            } => {
+                let in_class = |c: char| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+                let found = in_class(c)
+                    || caseless
+                        && (c.to_lowercase().any(in_class) || c.to_uppercase().any(in_class));
+                found != negated
+            }
+        }
+    }
+}
+
+// Matches a name against the tokens of a component.
+fn matches(tokens: &[Token], name: &str, caseless: bool) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    let (mut t, mut n) = (0, 0);
+    // position of the last star, and of the name when it was reached.
+    let mut star = None;
+    while n < name.len() {
+        match tokens.get(t) {
+            Some(Token::Star) => {
+                star = Some((t, n));
+                t += 1;
+            }
+            Some(token) if token.matches(name[n], caseless) => {
+                t += 1;
+                n += 1;
+            }
+            // let the last star match one more character.
+            _ => match star {
+                Some((st, sn)) => {
+                    t = st + 1;
+                    n = sn + 1;
+                    star = Some((st, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..].iter().all(|t| matches!(t, Token::Star))
+}
@@ -0,0 +1,246 @@
+//! Gzip decoder that can resume decompression from checkpoints.
+//!
+//! A checkpoint is a copy of the state of the decompressor (including the
+//! 32KiB sliding window) at a given offset of the compressed stream. Resuming
+//! from a checkpoint only requires seeking the compressed stream to that
+//! offset, similar to zlib's zran example.
+use std::io::{self, ErrorKind, Read};
+
+use flate2::Crc;
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+
+const BUFFER_SIZE: usize = 32 * 1024;
+
+// gzip header flags
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// Decompressor state at a given point of the gzip stream.
+#[derive(Clone)]
+pub(crate) struct Checkpoint {
+    /// Offset in the compressed stream.
+    in_offset: u64,
+    /// Offset in the decompressed stream.
+    out_offset: u64,
+    state: Box<InflateState>,
+}
+
+impl Checkpoint {
+    pub(crate) fn in_offset(&self) -> u64 {
+        self.in_offset
+    }
+
+    pub(crate) fn out_offset(&self) -> u64 {
+        self.out_offset
+    }
+}
+
+/// Returns the last checkpoint before the given offset of the decompressed
+/// stream.
+pub(crate) fn nearest(checkpoints: &[Checkpoint], out_offset: u64) -> Option<&Checkpoint> {
+    let idx = checkpoints.partition_point(|c| c.out_offset <= out_offset);
+    if idx == 0 {
+        None
+    } else {
+        checkpoints.get(idx - 1)
+    }
+}
+
+/// Gzip decoder (single member, like flate2's `GzDecoder`).
+///
+/// The size in the trailer is checked at the end of the stream. The CRC is only
+/// checked when decompression started from the beginning of the stream, since
+/// checkpoints don't keep the CRC of the data before them.
+pub(crate) struct GzReader<R> {
+    inner: R,
+    state: Box<InflateState>,
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    in_offset: u64,
+    out_offset: u64,
+    header: bool,
+    done: bool,
+    // CRC of the decompressed data, unless resumed from a checkpoint.
+    crc: Option<Crc>,
+    // interval and checkpoints recorded so far.
+    record: Option<(u64, Vec<Checkpoint>)>,
+}
+
+impl<R: Read> GzReader<R> {
+    /// Decompress from the start of a gzip stream.
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: InflateState::new_boxed(DataFormat::Raw),
+            buf: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            in_offset: 0,
+            out_offset: 0,
+            header: true,
+            done: false,
+            crc: Some(Crc::new()),
+            record: None,
+        }
+    }
+
+    /// Resume decompression from a checkpoint.
+    ///
+    /// The inner reader must be positioned at the compressed offset of the
+    /// checkpoint.
+    pub(crate) fn resume(inner: R, checkpoint: &Checkpoint) -> Self {
+        Self {
+            state: checkpoint.state.clone(),
+            in_offset: checkpoint.in_offset,
+            out_offset: checkpoint.out_offset,
+            header: false,
+            crc: None,
+            ..Self::new(inner)
+        }
+    }
+
+    /// Records a checkpoint every `interval` bytes of compressed input.
+    ///
+    /// Checkpoints are appended to the given list, which may contain the
+    /// checkpoints of a previous read of the same stream.
+    pub(crate) fn record(mut self, interval: u64, checkpoints: Vec<Checkpoint>) -> Self {
+        self.record = Some((interval.max(1), checkpoints));
+        self
+    }
+
+    /// Returns the recorded checkpoints.
+    pub(crate) fn into_checkpoints(self) -> Vec<Checkpoint> {
+        self.record.map(|(_, c)| c).unwrap_or_default()
+    }
+
+    // Fills the input buffer if it's empty. Returns false at the end of the stream.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.pos == self.cap {
+            self.pos = 0;
+            self.cap = self.inner.read(&mut self.buf)?;
+        }
+        Ok(self.pos < self.cap)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        if !self.fill()? {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let byte = self.buf[self.pos];
+        self.pos += 1;
+        self.in_offset += 1;
+        Ok(byte)
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let (id1, id2, cm) = (self.byte()?, self.byte()?, self.byte()?);
+        if id1 != 0x1f || id2 != 0x8b || cm != 8 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Invalid gzip header.",
+            ));
+        }
+        let flags = self.byte()?;
+        // mtime, extra flags & os
+        for _ in 0..6 {
+            self.byte()?;
+        }
+        if flags & FEXTRA != 0 {
+            let len = u16::from(self.byte()?) | u16::from(self.byte()?) << 8;
+            for _ in 0..len {
+                self.byte()?;
+            }
+        }
+        if flags & FNAME != 0 {
+            while self.byte()? != 0 {}
+        }
+        if flags & FCOMMENT != 0 {
+            while self.byte()? != 0 {}
+        }
+        if flags & FHCRC != 0 {
+            self.byte()?;
+            self.byte()?;
+        }
+        Ok(())
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        let crc = u32::from_le_bytes([self.byte()?, self.byte()?, self.byte()?, self.byte()?]);
+        let size = u32::from_le_bytes([self.byte()?, self.byte()?, self.byte()?, self.byte()?]);
+        let crc_ok = self.crc.as_ref().is_none_or(|c| c.sum() == crc);
+        // the size is stored modulo 2^32
+        if !crc_ok || size != self.out_offset as u32 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "Corrupt gzip stream.",
+            ));
+        }
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) {
+        if let Some((interval, ref mut checkpoints)) = self.record {
+            let next = checkpoints
+                .last()
+                .map(|c| c.in_offset + interval)
+                .unwrap_or(interval);
+            if self.in_offset >= next {
+                checkpoints.push(Checkpoint {
+                    in_offset: self.in_offset,
+                    out_offset: self.out_offset,
+                    state: self.state.clone(),
+                });
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for GzReader<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.done || out.is_empty() {
+            return Ok(0);
+        }
+        if self.header {
+            self.read_header()?;
+            self.header = false;
+        }
+        loop {
+            self.checkpoint();
+            let eof = !self.fill()?;
+            let res = inflate(
+                &mut self.state,
+                &self.buf[self.pos..self.cap],
+                out,
+                MZFlush::None,
+            );
+            self.pos += res.bytes_consumed;
+            self.in_offset += res.bytes_consumed as u64;
+            self.out_offset += res.bytes_written as u64;
+            if let Some(crc) = &mut self.crc {
+                crc.update(&out[..res.bytes_written]);
+            }
+
+            match res.status {
+                Ok(MZStatus::StreamEnd) => {
+                    // anything after the trailer (like other members) is ignored.
+                    self.done = true;
+                    self.read_trailer()?;
+                    return Ok(res.bytes_written);
+                }
+                Ok(_) | Err(MZError::Buf) if res.bytes_written > 0 => return Ok(res.bytes_written),
+                Ok(_) | Err(MZError::Buf) if eof => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(_) | Err(MZError::Buf) => {}
+                Err(_) => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        "Corrupt deflate stream.",
+                    ))
+                }
+            }
+        }
+    }
+}
@@ -4,21 +4,36 @@ use std::borrow::Cow;
 use std::collections::btree_map::{BTreeMap, Iter};
 use std::collections::vec_deque::VecDeque;
 use std::ffi::{OsStr, OsString};
-use std::path::{Path, PathBuf};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Maximum number of symbolic links followed when resolving a path.
+const MAX_LINKS: usize = 40;
 
 /// Directory tree node.
 /// Contains a list of file entries (leaf nodes), and directories (child nodes).
 struct Node<M> {
     files: BTreeMap<OsString, M>,
     dirs: BTreeMap<OsString, Node<M>>,
+    // Directories inserted with `insert_dir` are kept when they become empty.
+    explicit: bool,
+    // Metadata of the directory itself, if it was inserted with some.
+    meta: Option<M>,
 }
 impl<M> Node<M> {
     fn new() -> Self {
         Self {
             files: BTreeMap::new(),
             dirs: BTreeMap::new(),
+            explicit: false,
+            meta: None,
         }
     }
+
+    // Returns true if the node can be pruned from the tree.
+    fn is_prunable(&self) -> bool {
+        !self.explicit && self.files.is_empty() && self.dirs.is_empty()
+    }
 }
 
 /// Directory index, implemented as a trie.
@@ -78,38 +93,106 @@ pub struct Entry<'a, M> {
     pub kind: EntryKind,
 }
 
+impl<M> Default for Index<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<M> Index<M> {
     pub fn new() -> Self {
         Self { root: Node::new() }
     }
 
-    pub fn entries<P: AsRef<Path>>(&self, path: P) -> Entries<M> {
+    pub fn entries<P: AsRef<Path>>(&self, path: P) -> Entries<'_, M> {
         let path = normalize_path(path.as_ref());
-        entries(path.into_iter().collect(), &self.root)
+        entries(path.iter().collect(), &self.root)
+    }
+
+    /// Removes a file from the index, returning its metadata.
+    ///
+    /// Parent directories left empty are removed too, unless they were inserted
+    /// with `insert_dir`.
+    pub fn remove<P: AsRef<Path>>(&mut self, path: P) -> Option<M> {
+        let path = normalize_path(path.as_ref());
+        remove(path.iter().collect(), &mut self.root)
+    }
+
+    /// Removes a directory and all of its contents from the index.
+    ///
+    /// Returns the removed files, with paths relative to the directory, or
+    /// `None` if the directory doesn't exist. Parent directories are pruned
+    /// like in `remove`.
+    pub fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> Option<Vec<(PathBuf, M)>> {
+        let path = normalize_path(path.as_ref());
+        let node = remove_dir(path.iter().collect(), &mut self.root)?;
+        let mut files = Vec::new();
+        drain(node, PathBuf::new(), &mut files);
+        Some(files)
+    }
+
+    /// Inserts a directory, along with any missing parent directories.
+    pub fn insert_dir<P: Into<PathBuf>>(&mut self, path: P) {
+        self.dir_mut(path.into()).explicit = true;
+    }
+
+    /// Inserts a directory with metadata of its own, like `insert_dir`.
+    pub fn insert_dir_meta<P: Into<PathBuf>>(&mut self, path: P, meta: M) {
+        let node = self.dir_mut(path.into());
+        node.explicit = true;
+        node.meta = Some(meta);
     }
 
-    pub fn remove<P: AsRef<Path>>(&self, _path: P) -> Option<M> {
-        unimplemented!()
+    // Returns the node of a directory, creating it if it doesn't exist.
+    fn dir_mut(&mut self, path: PathBuf) -> &mut Node<M> {
+        let path = normalize_path(&path);
+        let mut node = &mut self.root;
+        for part in path.iter() {
+            node.files.remove(part);
+            node = node
+                .dirs
+                .entry(part.to_os_string())
+                .or_insert_with(Node::new);
+        }
+        node
     }
 
     pub fn insert<P: Into<PathBuf>>(&mut self, path: P, meta: M) {
         let path = path.into();
         let path = normalize_path(&path);
-        insert(path.into_iter().collect(), &mut self.root, meta)
+        insert(path.iter().collect(), &mut self.root, meta)
     }
 
     pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&M> {
         let path = normalize_path(path.as_ref());
-        get(path.into_iter().collect(), &self.root)
+        get(path.iter().collect(), &self.root)
     }
 
     pub fn contains<P: AsRef<Path>>(&self, path: P) -> bool {
         self.get(path).is_some()
     }
 
+    /// Returns true if the path points to a directory of the index.
+    pub fn is_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.dir(path.as_ref()).is_some()
+    }
+
+    /// Returns the metadata of a directory inserted with `insert_dir_meta`.
+    pub fn dir_meta<P: AsRef<Path>>(&self, path: P) -> Option<&M> {
+        self.dir(path.as_ref())?.meta.as_ref()
+    }
+
+    fn dir(&self, path: &Path) -> Option<&Node<M>> {
+        let path = normalize_path(path);
+        let mut node = &self.root;
+        for part in path.iter() {
+            node = node.dirs.get(part)?;
+        }
+        Some(node)
+    }
+
     pub fn clear(&mut self) {
-        self.root.files.clear();
-        self.root.dirs.clear();
+        self.root = Node::new();
     }
 }
 
@@ -138,7 +221,7 @@ fn insert<M>(mut parts: VecDeque<&OsStr>, node: &mut Node<M>, meta: M) {
     match (f0, parts.front()) {
         (None, _) => {}
         (Some(file), None) => {
-            if node.dirs.get(file).is_none() {
+            if !node.dirs.contains_key(file) {
                 node.files.insert(file.to_os_string(), meta);
             }
         }
@@ -156,6 +239,48 @@ fn insert<M>(mut parts: VecDeque<&OsStr>, node: &mut Node<M>, meta: M) {
     }
 }
 
+fn remove<M>(mut parts: VecDeque<&OsStr>, node: &mut Node<M>) -> Option<M> {
+    let f0 = parts.pop_front();
+    match (f0, parts.front()) {
+        (None, _) => None,
+        (Some(file), None) => node.files.remove(file),
+        (Some(dir), Some(_)) => {
+            let child = node.dirs.get_mut(dir)?;
+            let meta = remove(parts, child);
+            if meta.is_some() && child.is_prunable() {
+                node.dirs.remove(dir);
+            }
+            meta
+        }
+    }
+}
+
+fn remove_dir<M>(mut parts: VecDeque<&OsStr>, node: &mut Node<M>) -> Option<Node<M>> {
+    let f0 = parts.pop_front();
+    match (f0, parts.front()) {
+        (None, _) => None,
+        (Some(dir), None) => node.dirs.remove(dir),
+        (Some(dir), Some(_)) => {
+            let child = node.dirs.get_mut(dir)?;
+            let removed = remove_dir(parts, child);
+            if removed.is_some() && child.is_prunable() {
+                node.dirs.remove(dir);
+            }
+            removed
+        }
+    }
+}
+
+// Moves the files of a node (and its children) into a list.
+fn drain<M>(node: Node<M>, path: PathBuf, out: &mut Vec<(PathBuf, M)>) {
+    for (name, meta) in node.files {
+        out.push((path.join(name), meta));
+    }
+    for (name, dir) in node.dirs {
+        drain(dir, path.join(name), out);
+    }
+}
+
 fn get<'a, M>(mut parts: VecDeque<&OsStr>, node: &'a Node<M>) -> Option<&'a M> {
     let f0 = parts.pop_front();
     match (f0, parts.front()) {
@@ -171,6 +296,57 @@ fn get<'a, M>(mut parts: VecDeque<&OsStr>, node: &'a Node<M>) -> Option<&'a M> {
     }
 }
 
+/// Resolves the symbolic links in every component of a path.
+///
+/// `read_link` returns the target of the link at the given path, if there is
+/// one. Relative targets are resolved against the directory of the link, and
+/// absolute targets against `root`.
+pub(crate) fn resolve_links<F>(path: &Path, root: &Path, read_link: F) -> io::Result<PathBuf>
+where
+    F: Fn(&Path) -> Option<PathBuf>,
+{
+    // components left to resolve, in reverse order.
+    let mut pending: Vec<OsString> = path.iter().rev().map(OsStr::to_os_string).collect();
+    let mut resolved = PathBuf::new();
+    let mut links = 0;
+    while let Some(part) = pending.pop() {
+        match Path::new(&part).components().next() {
+            Some(Component::RootDir) | Some(Component::Prefix(_)) => {
+                resolved = root.to_path_buf();
+            }
+            Some(Component::ParentDir) => {
+                if resolved != root {
+                    resolved.pop();
+                }
+            }
+            Some(Component::Normal(name)) => {
+                resolved.push(name);
+                if let Some(target) = read_link(&resolved) {
+                    links += 1;
+                    if links > MAX_LINKS {
+                        return Err(io::Error::other("Too many levels of symbolic links."));
+                    }
+                    resolved.pop();
+                    pending.extend(target.iter().rev().map(OsStr::to_os_string));
+                }
+            }
+            Some(Component::CurDir) | None => {}
+        }
+    }
+    Ok(resolved)
+}
+
+/// Like `resolve_links`, but the last component of the path isn't followed.
+pub(crate) fn resolve_parent_links<F>(path: &Path, root: &Path, read_link: F) -> io::Result<PathBuf>
+where
+    F: Fn(&Path) -> Option<PathBuf>,
+{
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => Ok(resolve_links(parent, root, read_link)?.join(name)),
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
 /// Normalizes path by removing any references to the parent (`..`) and the
 /// current (`.`) directory.
 ///
@@ -182,12 +358,9 @@ fn get<'a, M>(mut parts: VecDeque<&OsStr>, node: &'a Node<M>) -> Option<&'a M> {
 /// assert_eq!(Path::new("foo"), normalize_path(Path::new("./foo")));
 /// ```
 #[doc(hidden)]
-pub fn normalize_path(path: &Path) -> Cow<Path> {
+pub fn normalize_path(path: &Path) -> Cow<'_, Path> {
     use std::path::Component::*;
-    if path.components().any(|c| match c {
-        CurDir | ParentDir => true,
-        _ => false,
-    }) {
+    if path.components().any(|c| matches!(c, CurDir | ParentDir)) {
         let mut normal = PathBuf::new();
         for comp in path.components() {
             match comp {
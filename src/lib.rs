@@ -4,8 +4,17 @@
 //!
 //! - Access to the local (native) filesystem.
 //! - In-memory filesystems.
-//! - Read from tar, tar.gz, and zip archives.
-//! - Filesystem overlays.
+//! - Read from tar, tar.gz, and zip archives (and tar.zst, tar.xz, and tar.bz2
+//!   with the `zstd`, `xz`, and `bzip2` features). Zip members compressed with
+//!   bzip2, zstd, lzma, or xz need the feature of the same name.
+//! - Pack the contents of any store into tar, tar.gz, and zip archives.
+//! - Memory-mapped files (`mmap` feature).
+//! - Write to the local and in-memory filesystems.
+//! - Filesystem overlays, with whiteouts to hide files of the lower layers.
+//! - Copy-on-write overlays of a read-only store and a writable one.
+//! - Recursive directory walks and glob patterns over any store.
+//! - Symbolic links in the local and in-memory filesystems, and tar archives.
+//! - Sharing a filesystem across threads (`MiniFs` is `Send + Sync`).
 //!
 //! ## Case sensitivity
 //!
@@ -40,48 +49,81 @@
 //! Don't use this crate in applications where security is a critical factor.
 //! [`LocalFs`] in particular might be vulnerable to [directory traversal
 //! attacks][dir], so it's best not to use it directly in a static file server,
-//! for example.
+//! for example. If you have to, create it with [`LocalFs::sandboxed`], which
+//! rejects paths that would escape its root directory.
 //!
 //! [`std::fs`]: https://doc.rust-lang.org/std/fs/index.html
 //! [`Store`]: ./trait.Store.html
 //! [`LocalFs`]: ./struct.LocalFs.html
+//! [`LocalFs::sandboxed`]: ./struct.LocalFs.html#method.sandboxed
 //! [dir]: https://en.wikipedia.org/wiki/Directory_traversal_attack
 #![deny(warnings)]
 use std::any::Any;
-use std::collections::LinkedList;
-use std::io::{Cursor, Error, ErrorKind, Read, Result, Seek, SeekFrom};
-use std::path::{Path, PathBuf};
-use std::rc::Rc;
+use std::collections::{BTreeSet, LinkedList};
+use std::ffi::OsString;
+use std::io::{Cursor, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::{env, fs};
 
+#[cfg(feature = "tokio")]
+pub use aio::{AsyncEntries, AsyncStore, AsyncStoreExt, Blocking};
 pub use caseless::CaselessFs;
+pub use glob::Glob;
+#[cfg(feature = "mmap")]
+pub use mmap::{MmapFile, MmapFs};
+pub use overlay::OverlayFs;
 //pub use index::{Index, IndexEntries};
-pub use store::{Entries, Entry, EntryKind, Store, StoreExt};
+pub use store::{
+    Entries, Entry, EntryKind, Metadata, OpenOptions, Store, StoreExt, StoreMut, StoreMutExt,
+};
 #[cfg(feature = "tar")]
 pub use tar::TarFs;
+pub use walk::{Walk, WalkEntry};
 #[cfg(feature = "zip")]
 pub use zip::ZipFs;
 
 include!("macros.rs");
 
+/// Async stores.
+#[cfg(feature = "tokio")]
+pub mod aio;
 pub mod caseless;
+pub mod glob;
+#[cfg(feature = "tar")]
+mod gzip;
 /// Directory index.
 #[doc(hidden)]
 pub mod index;
+/// Memory-mapped files.
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod overlay;
 mod store;
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod stream;
+mod sync;
 /// Tar file storage.
 #[cfg(feature = "tar")]
 pub mod tar;
+pub mod walk;
+pub mod whiteout;
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod window;
 /// Zip file storage.
 #[cfg(feature = "zip")]
 pub mod zip;
 /// Convenient library imports.
 pub mod prelude {
-    pub use crate::store::{Store, StoreExt};
+    pub use crate::store::{Store, StoreExt, StoreMut, StoreMutExt};
 }
 
 impl_file! {
     /// File you can seek and read from.
+    ///
+    /// Files opened from a [`StoreMut`] can also be written to.
+    ///
+    /// [`StoreMut`]: ./trait.StoreMut.html
     pub enum File {
         Local(fs::File),
         Ram(RamFile),
@@ -89,11 +131,50 @@ impl_file! {
         Zip(zip::ZipFsFile),
         #[cfg(feature = "tar")]
         Tar(tar::TarFsFile),
+        #[cfg(feature = "mmap")]
+        Mmap(mmap::MmapFile),
         // External types are dynamic
         User(Box<dyn UserFile>),
     }
 }
 
+impl Write for File {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self {
+            File::Local(ref mut file) => file.write(buf),
+            File::Ram(ref mut file) => file.write(buf),
+            _ => Err(Error::new(ErrorKind::PermissionDenied, "Read-only file.")),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            File::Local(ref mut file) => file.flush(),
+            File::Ram(ref mut file) => file.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl File {
+    /// Returns the contents of the file if they are already in memory.
+    ///
+    /// Memory-mapped files, and files from the in-memory and archive stores
+    /// that have been read into memory can be accessed without copying them.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            File::Ram(ref file) => Some(file.as_bytes()),
+            #[cfg(feature = "zip")]
+            File::Zip(ref file) => file.as_bytes(),
+            #[cfg(feature = "tar")]
+            File::Tar(ref file) => file.as_bytes(),
+            #[cfg(feature = "mmap")]
+            File::Mmap(ref file) => Some(file.as_bytes()),
+            _ => None,
+        }
+    }
+}
+
 /// Custom file type.
 pub trait UserFile: Any + Read + Seek + Send {}
 
@@ -105,47 +186,169 @@ impl<T: UserFile> From<T> for File {
 
 struct Mount {
     path: PathBuf,
-    store: Box<dyn Store<File = File>>,
+    store: Box<dyn StoreMut<File = File> + Send + Sync>,
 }
 
 /// Virtual filesystem.
+///
+/// Mounted stores must be `Send + Sync`, so a `MiniFs` can be shared between
+/// threads (for example, with an `Arc`).
+///
+/// Mount points are listed as directories of their parent paths, even if no
+/// store is mounted there.
+///
+/// When mounts overlap, the most recent one is used. See [`MountPolicy`] to
+/// fall back to earlier mounts instead.
+///
+/// [`MountPolicy`]: ./enum.MountPolicy.html
 pub struct MiniFs {
     mount: LinkedList<Mount>,
+    policy: MountPolicy,
+}
+
+/// How [`MiniFs`] handles paths covered by more than one mount.
+///
+/// [`MiniFs`]: ./struct.MiniFs.html
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum MountPolicy {
+    /// Only the most recent mount is used, even if it doesn't contain the
+    /// path.
+    #[default]
+    Shadow,
+    /// Fall back to earlier mounts when the most recent one returns a
+    /// `NotFound` error, like the tuple and `Vec` overlays. Listings contain
+    /// the entries of all the mounts.
+    Fallthrough,
 }
 
 impl Store for MiniFs {
     type File = File;
 
     fn open_path(&self, path: &Path) -> Result<File> {
-        let next = self.mount.iter().rev().find_map(|mnt| {
-            if let Ok(np) = path.strip_prefix(&mnt.path) {
-                Some((np, &mnt.store))
-            } else {
-                None
+        self.with_mounts(path, |store, np| store.open_path(np))
+    }
+
+    /// Lists the entries of the store mounted at the path, along with the
+    /// mount points below it, which are listed as directories.
+    ///
+    /// With [`MountPolicy::Fallthrough`], the entries of all the stores mounted
+    /// at the path are listed, skipping duplicates.
+    ///
+    /// [`MountPolicy::Fallthrough`]: ./enum.MountPolicy.html#variant.Fallthrough
+    fn entries_path(&self, path: &Path) -> Result<Entries<'_>> {
+        let mounts = self.mount_points(path);
+        let mut listings = Vec::new();
+        let mut not_found = None;
+        for (np, store) in self.find_mounts(path) {
+            match store.entries_path(np) {
+                Ok(entries) => listings.push(entries),
+                Err(e) if e.kind() == ErrorKind::NotFound => not_found = Some(e),
+                Err(e) => return Err(e),
+            }
+            if self.policy == MountPolicy::Shadow {
+                break;
+            }
+        }
+        if listings.is_empty() && mounts.is_empty() {
+            if let Some(e) = not_found {
+                return Err(e);
             }
+        }
+
+        // mount points shadow the entries of the stores with the same name.
+        let mut seen = mounts.clone();
+        let entries = listings
+            .into_iter()
+            .flatten()
+            .filter(move |ent| match *ent {
+                Ok(ref ent) => seen.insert(ent.file_name().to_os_string()),
+                Err(_) => true,
+            });
+        let mounts = mounts.into_iter().map(|name| {
+            Ok(Entry {
+                name,
+                kind: EntryKind::Dir,
+            })
         });
-        if let Some((np, store)) = next {
-            store.open_path(np)
-        } else {
-            Err(Error::from(ErrorKind::NotFound))
+        Ok(Entries::new(entries.chain(mounts)))
+    }
+
+    fn metadata_path(&self, path: &Path) -> Result<Metadata> {
+        match self.with_mounts(path, |store, np| store.metadata_path(np)) {
+            Err(ref e)
+                if e.kind() == ErrorKind::NotFound && !self.mount_points(path).is_empty() =>
+            {
+                Ok(Metadata::dir())
+            }
+            meta => meta,
         }
     }
 
-    fn entries_path(&self, path: &Path) -> Result<Entries> {
-        // FIXME creating a new PathBuf because otherwise I'm getting lifetime errors.
-        let path = path.to_path_buf();
+    fn read_link_path(&self, path: &Path) -> Result<PathBuf> {
+        self.with_mounts(path, |store, np| store.read_link_path(np))
+    }
+
+    /// Asks the store mounted at the path, or all of them with
+    /// [`MountPolicy::Fallthrough`].
+    ///
+    /// [`MountPolicy::Fallthrough`]: ./enum.MountPolicy.html#variant.Fallthrough
+    fn case_sensitive_path(&self, path: &Path) -> bool {
+        let mounts = match self.policy {
+            MountPolicy::Shadow => 1,
+            MountPolicy::Fallthrough => usize::MAX,
+        };
+        self.find_mounts(path)
+            .take(mounts)
+            .all(|(np, store)| store.case_sensitive_path(np))
+    }
+}
+
+impl StoreMut for MiniFs {
+    fn open_with_path(&self, path: &Path, options: &OpenOptions) -> Result<File> {
+        self.with_mounts(path, |store, np| store.open_with_path(np, options))
+    }
+
+    fn remove_file_path(&self, path: &Path) -> Result<()> {
+        self.with_mounts(path, |store, np| store.remove_file_path(np))
+    }
 
-        Ok(Entries::new(
-            self.mount
-                .iter()
-                .rev()
-                .find(|m| path.strip_prefix(&m.path).is_ok())
-                .into_iter()
-                .flat_map(move |m| match path.strip_prefix(&m.path) {
-                    Ok(np) => m.store.entries_path(np).unwrap(),
-                    Err(_) => Entries::new(None),
-                }),
-        ))
+    fn create_dir_path(&self, path: &Path) -> Result<()> {
+        self.with_mounts(path, |store, np| store.create_dir_path(np))
+    }
+
+    /// Renames a file within a mounted store.
+    ///
+    /// Both paths must resolve to the same mount. With
+    /// [`MountPolicy::Fallthrough`], the file is renamed in the first mount
+    /// that has it, and the destination can be covered by later mounts.
+    ///
+    /// [`MountPolicy::Fallthrough`]: ./enum.MountPolicy.html#variant.Fallthrough
+    fn rename_path(&self, from: &Path, to: &Path) -> Result<()> {
+        let mounts = match self.policy {
+            MountPolicy::Shadow => 1,
+            MountPolicy::Fallthrough => usize::MAX,
+        };
+        self.with_mounts(from, |store, from| {
+            let to = self
+                .find_mounts(to)
+                .take(mounts)
+                .find(|&(_, other)| std::ptr::addr_eq(store, other));
+            match to {
+                Some((to, _)) => store.rename_path(from, to),
+                // look for the file in the next mounts if this one doesn't
+                // have it.
+                None => match store.metadata_path(from) {
+                    Err(e) if e.kind() == ErrorKind::NotFound => Err(e),
+                    _ => Err(Error::other("Can't rename files across mounts.")),
+                },
+            }
+        })
+    }
+}
+
+impl Default for MiniFs {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -153,13 +356,39 @@ impl MiniFs {
     pub fn new() -> Self {
         Self {
             mount: LinkedList::new(),
+            policy: MountPolicy::Shadow,
         }
     }
 
+    /// Sets how paths covered by more than one mount are handled.
+    pub fn mount_policy(mut self, policy: MountPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     pub fn mount<P, S, T>(mut self, path: P, store: S) -> Self
     where
         P: Into<PathBuf>,
-        S: Store<File = T> + 'static,
+        S: Store<File = T> + Send + Sync + 'static,
+        T: Into<File>,
+    {
+        let path = path.into();
+        let store = store::MapFile::new(store, |file: T| file.into());
+        let store = Box::new(store::ReadOnly(store));
+        self.mount.push_back(Mount { path, store });
+        self
+    }
+
+    /// Mounts a store that can be modified.
+    ///
+    /// Stores mounted with `mount` are read-only, even if they implement
+    /// [`StoreMut`].
+    ///
+    /// [`StoreMut`]: ./trait.StoreMut.html
+    pub fn mount_mut<P, S, T>(mut self, path: P, store: S) -> Self
+    where
+        P: Into<PathBuf>,
+        S: StoreMut<File = T> + Send + Sync + 'static,
         T: Into<File>,
     {
         let path = path.into();
@@ -168,14 +397,63 @@ impl MiniFs {
         self
     }
 
-    pub fn umount<P>(&mut self, path: P) -> Option<Box<dyn Store<File = File>>>
+    // Finds the mounted stores whose path is a prefix of the given path, the
+    // most recent first.
+    fn find_mounts<'a, 'p>(
+        &'a self,
+        path: &'p Path,
+    ) -> impl Iterator<Item = (&'p Path, &'a dyn StoreMut<File = File>)> {
+        self.mount.iter().rev().filter_map(move |mnt| {
+            if let Ok(np) = path.strip_prefix(&mnt.path) {
+                Some((np, &*mnt.store as &dyn StoreMut<File = File>))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Runs an operation on the mounts of the path, falling back to earlier
+    // mounts on `NotFound` errors if the policy allows it.
+    fn with_mounts<T, F>(&self, path: &Path, f: F) -> Result<T>
+    where
+        F: Fn(&dyn StoreMut<File = File>, &Path) -> Result<T>,
+    {
+        let mut res = Err(Error::from(ErrorKind::NotFound));
+        for (np, store) in self.find_mounts(path) {
+            res = f(store, np);
+            match res {
+                Err(ref e)
+                    if e.kind() == ErrorKind::NotFound
+                        && self.policy == MountPolicy::Fallthrough => {}
+                _ => break,
+            }
+        }
+        res
+    }
+
+    // Names of the directories below the path that lead to a mount point.
+    fn mount_points(&self, path: &Path) -> BTreeSet<OsString> {
+        self.mount
+            .iter()
+            .filter_map(
+                |mnt| match mnt.path.strip_prefix(path).ok()?.components().next()? {
+                    Component::Normal(name) => Some(name.to_os_string()),
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    pub fn umount<P>(&mut self, path: P) -> Option<Box<dyn Store<File = File> + Send + Sync>>
     where
         P: AsRef<Path>,
     {
         let path = path.as_ref();
         if let Some(p) = self.mount.iter().rposition(|p| p.path == path) {
             let mut tail = self.mount.split_off(p);
-            let fs = tail.pop_front().map(|m| m.store);
+            let fs = tail
+                .pop_front()
+                .map(|m| m.store as Box<dyn Store<File = File> + Send + Sync>);
             self.mount.append(&mut tail);
             fs
         } else {
@@ -185,8 +463,19 @@ impl MiniFs {
 }
 
 /// Native file store.
+///
+/// Paths are joined to the root directory as they are, so they can refer to
+/// files outside of it. Use [`LocalFs::sandboxed`] to restrict access to the
+/// root directory.
+///
+/// Symbolic links are followed, unless disabled with [`LocalFs::follow_links`].
+///
+/// [`LocalFs::sandboxed`]: #method.sandboxed
+/// [`LocalFs::follow_links`]: #method.follow_links
 pub struct LocalFs {
     root: PathBuf,
+    sandboxed: bool,
+    follow_links: bool,
 }
 
 impl Store for LocalFs {
@@ -197,30 +486,27 @@ impl Store for LocalFs {
             .create(false)
             .read(true)
             .write(false)
-            .open(self.root.join(path))
+            .open(self.resolve_file(path)?)
     }
 
-    fn entries_path(&self, path: &Path) -> Result<Entries> {
+    fn entries_path(&self, path: &Path) -> Result<Entries<'_>> {
         // FIXME cloned because lifetimes.
         //let root = self.root.clone();
 
-        let entries = fs::read_dir(self.root.join(path))?.map(move |ent| {
+        let entries = fs::read_dir(self.resolve(path)?)?.map(move |ent| {
             let entry = ent?;
             let path = entry
                 .path()
                 .strip_prefix(&self.root)
                 .map(Path::to_path_buf)
                 .expect("Error striping path suffix.");
-            let file_type = entry.file_type()?;
-
-            // TODO synlinks
-            let kind = if file_type.is_dir() {
-                EntryKind::Dir
-            } else if file_type.is_symlink() {
-                EntryKind::File
-            } else {
-                EntryKind::File
-            };
+            let mut kind = local_kind(entry.file_type()?);
+            // dangling links are listed as links.
+            if kind == EntryKind::Symlink && self.follow_links {
+                if let Ok(meta) = fs::metadata(entry.path()) {
+                    kind = local_kind(meta.file_type());
+                }
+            }
 
             Ok(Entry {
                 name: path.into_os_string(),
@@ -230,36 +516,347 @@ impl Store for LocalFs {
 
         Ok(Entries::new(entries))
     }
+
+    fn metadata_path(&self, path: &Path) -> Result<Metadata> {
+        let path = self.resolve(path)?;
+        let meta = if self.follow_links {
+            fs::metadata(path)?
+        } else {
+            fs::symlink_metadata(path)?
+        };
+        Ok(local_metadata(&meta))
+    }
+
+    fn read_link_path(&self, path: &Path) -> Result<PathBuf> {
+        fs::read_link(self.resolve(path)?)
+    }
+}
+
+fn sandbox_path(root: &Path, path: &Path) -> Result<PathBuf> {
+    let escapes = || {
+        Error::new(
+            ErrorKind::PermissionDenied,
+            "Path escapes the root of the store.",
+        )
+    };
+
+    let mut resolved = root.to_path_buf();
+    let mut depth = 0;
+    for comp in path.components() {
+        match comp {
+            Component::CurDir => {}
+            Component::Normal(part) => {
+                resolved.push(part);
+                depth += 1;
+            }
+            Component::ParentDir if depth > 0 => {
+                resolved.pop();
+                depth -= 1;
+            }
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(escapes())
+            }
+        }
+    }
+
+    // follow symlinks of the deepest path that exists.
+    let root = fs::canonicalize(root)?;
+    let mut existing = resolved.as_path();
+    let canonical = loop {
+        match fs::canonicalize(existing) {
+            Ok(canonical) => break canonical,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {
+                // dangling symlinks could be used to create files anywhere.
+                if fs::symlink_metadata(existing).is_ok() {
+                    return Err(escapes());
+                }
+                existing = existing.parent().ok_or_else(escapes)?;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    if canonical.starts_with(&root) {
+        Ok(resolved)
+    } else {
+        Err(escapes())
+    }
+}
+
+fn local_kind(file_type: fs::FileType) -> EntryKind {
+    if file_type.is_dir() {
+        EntryKind::Dir
+    } else if file_type.is_symlink() {
+        EntryKind::Symlink
+    } else {
+        EntryKind::File
+    }
+}
+
+fn local_metadata(meta: &fs::Metadata) -> Metadata {
+    let kind = local_kind(meta.file_type());
+
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        Some(meta.permissions().mode())
+    };
+    #[cfg(not(unix))]
+    let mode = None;
+
+    Metadata {
+        len: if meta.is_dir() { 0 } else { meta.len() },
+        kind,
+        modified: meta.modified().ok(),
+        mode,
+    }
+}
+
+impl StoreMut for LocalFs {
+    fn open_with_path(&self, path: &Path, options: &OpenOptions) -> Result<fs::File> {
+        fs::OpenOptions::new()
+            .read(options.read)
+            .write(options.write)
+            .append(options.append)
+            .truncate(options.truncate)
+            .create(options.create)
+            .create_new(options.create_new)
+            .open(self.resolve_file(path)?)
+    }
+
+    fn remove_file_path(&self, path: &Path) -> Result<()> {
+        fs::remove_file(self.resolve(path)?)
+    }
+
+    fn create_dir_path(&self, path: &Path) -> Result<()> {
+        fs::create_dir(self.resolve(path)?)
+    }
+
+    fn rename_path(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(self.resolve(from)?, self.resolve(to)?)
+    }
 }
 
 impl LocalFs {
     pub fn new<P: Into<PathBuf>>(root: P) -> Self {
-        Self { root: root.into() }
+        Self {
+            root: root.into(),
+            sandboxed: false,
+            follow_links: true,
+        }
+    }
+
+    /// Native file store that can't access files outside of its root.
+    ///
+    /// Absolute paths, paths that go above the root with `..`, and paths that
+    /// resolve to a location outside of the root through a symlink are
+    /// rejected with a `PermissionDenied` error.
+    ///
+    /// Symlinks are resolved before the file is opened, so a symlink that is
+    /// modified in between can still escape the root.
+    pub fn sandboxed<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            sandboxed: true,
+            follow_links: true,
+        }
+    }
+
+    /// Whether to follow symbolic links (`true` by default).
+    ///
+    /// Followed links are listed with the kind of their target. Walks (and
+    /// globs) still don't descend into links to directories, so links to a
+    /// parent directory don't make them loop forever.
+    ///
+    /// When links aren't followed, they are listed with the `Symlink` kind,
+    /// their metadata describes the link itself, and opening them fails.
+    /// Links in the parent directories of a path are still followed.
+    pub fn follow_links(mut self, follow: bool) -> Self {
+        self.follow_links = follow;
+        self
+    }
+
+    // Path of a file of the store in the native filesystem.
+    fn resolve(&self, path: &Path) -> Result<PathBuf> {
+        if self.sandboxed {
+            sandbox_path(&self.root, path)
+        } else {
+            Ok(self.root.join(path))
+        }
+    }
+
+    // Path of a file that is about to be opened.
+    fn resolve_file(&self, path: &Path) -> Result<PathBuf> {
+        let path = self.resolve(path)?;
+        if !self.follow_links {
+            match fs::symlink_metadata(&path) {
+                Ok(ref meta) if meta.file_type().is_symlink() => {
+                    return Err(Error::other("Is a symbolic link."))
+                }
+                _ => {}
+            }
+        }
+        Ok(path)
     }
 
     /// Point to the current working directory.
     pub fn pwd() -> Result<Self> {
         Ok(Self::new(env::current_dir()?))
     }
+
+    /// Memory-map files instead of reading them.
+    ///
+    /// See the [`mmap`] module for the safety caveats.
+    ///
+    /// [`mmap`]: ./mmap/index.html
+    #[cfg(feature = "mmap")]
+    pub fn mmap(self) -> MmapFs {
+        MmapFs::new(self)
+    }
 }
 
 /// In-memory file storage
 pub struct RamFs {
-    index: index::Index<Rc<[u8]>>,
+    index: RamIndex,
+}
+
+type RamIndex = Arc<RwLock<index::Index<RamNode>>>;
+
+// Files and symbolic links of a RamFs.
+enum RamNode {
+    File(Arc<[u8]>),
+    Symlink(PathBuf),
+}
+
+impl RamNode {
+    fn into_file(self) -> Option<Arc<[u8]>> {
+        match self {
+            RamNode::File(file) => Some(file),
+            RamNode::Symlink(_) => None,
+        }
+    }
+}
+
+fn read_index(index: &RamIndex) -> RwLockReadGuard<'_, index::Index<RamNode>> {
+    sync::read(index)
+}
+
+fn write_index(index: &RamIndex) -> RwLockWriteGuard<'_, index::Index<RamNode>> {
+    sync::write(index)
+}
+
+// Returns the target of the link at the given path, if there is one.
+fn ram_link(index: &index::Index<RamNode>) -> impl Fn(&Path) -> Option<PathBuf> + '_ {
+    move |path| match index.get(path) {
+        Some(RamNode::Symlink(target)) => Some(target.clone()),
+        _ => None,
+    }
+}
+
+// Follows the symbolic links of a path. Absolute targets are kept as they are.
+fn ram_resolve(index: &index::Index<RamNode>, path: &Path) -> Result<PathBuf> {
+    index::resolve_links(path, Path::new("/"), ram_link(index))
+}
+
+// Returns the data of the file at the given path, following links.
+fn ram_file(index: &index::Index<RamNode>, path: &Path) -> Result<Arc<[u8]>> {
+    match index.get(ram_resolve(index, path)?) {
+        Some(RamNode::File(file)) => Ok(Arc::clone(file)),
+        _ => Err(Error::from(ErrorKind::NotFound)),
+    }
 }
 
 /// In-memory file.
-pub struct RamFile(Cursor<Rc<[u8]>>);
+pub struct RamFile(RamFileInner);
+
+impl RamFile {
+    /// Returns the contents of the file, including any unflushed writes.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self.0 {
+            RamFileInner::Read(ref data) => data.get_ref(),
+            RamFileInner::Write { ref data, .. } => data.get_ref(),
+        }
+    }
+}
+
+enum RamFileInner {
+    Read(Cursor<Arc<[u8]>>),
+    // Files opened for writing work on a copy of the data, which is committed
+    // back to the store when the file is flushed or dropped. Nothing is
+    // committed if the file has been removed (or renamed) in the meantime.
+    Write {
+        data: Cursor<Vec<u8>>,
+        append: bool,
+        dirty: bool,
+        path: PathBuf,
+        index: RamIndex,
+    },
+}
 
 impl Read for RamFile {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        self.0.read(buf)
+        match self.0 {
+            RamFileInner::Read(ref mut data) => data.read(buf),
+            RamFileInner::Write { ref mut data, .. } => data.read(buf),
+        }
     }
 }
 
 impl Seek for RamFile {
     fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
-        self.0.seek(pos)
+        match self.0 {
+            RamFileInner::Read(ref mut data) => data.seek(pos),
+            RamFileInner::Write { ref mut data, .. } => data.seek(pos),
+        }
+    }
+}
+
+impl Write for RamFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self.0 {
+            RamFileInner::Read(_) => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                "File not opened for writing.",
+            )),
+            RamFileInner::Write {
+                ref mut data,
+                append,
+                ref mut dirty,
+                ..
+            } => {
+                if append {
+                    data.seek(SeekFrom::End(0))?;
+                }
+                *dirty = true;
+                data.write(buf)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let RamFileInner::Write {
+            ref data,
+            ref mut dirty,
+            ref path,
+            ref index,
+            ..
+        } = self.0
+        {
+            if *dirty {
+                let mut index = write_index(index);
+                if let Some(RamNode::File(_)) = index.get(path) {
+                    let file: Arc<[u8]> = data.get_ref().as_slice().into();
+                    index.insert(path.clone(), RamNode::File(file));
+                }
+                *dirty = false;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RamFile {
+    fn drop(&mut self) {
+        let _ = self.flush();
     }
 }
 
@@ -267,43 +864,204 @@ impl Store for RamFs {
     type File = RamFile;
 
     fn open_path(&self, path: &Path) -> Result<Self::File> {
-        match self.index.get(path) {
-            Some(file) => Ok(RamFile(Cursor::new(Rc::clone(file)))),
+        let file = ram_file(&read_index(&self.index), path)?;
+        Ok(RamFile(RamFileInner::Read(Cursor::new(file))))
+    }
+
+    fn entries_path(&self, path: &Path) -> Result<Entries<'_>> {
+        let index = read_index(&self.index);
+        let entries: Vec<_> = index
+            .entries(path)
+            .map(|ent| {
+                let kind = match ent.meta {
+                    Some(RamNode::Symlink(_)) => EntryKind::Symlink,
+                    _ => ent.kind,
+                };
+                Ok(Entry {
+                    name: ent.name.to_os_string(),
+                    kind,
+                })
+            })
+            .collect();
+        Ok(Entries::new(entries))
+    }
+
+    fn metadata_path(&self, path: &Path) -> Result<Metadata> {
+        let index = read_index(&self.index);
+        let path = ram_resolve(&index, path)?;
+        if let Some(RamNode::File(file)) = index.get(&path) {
+            Ok(Metadata::file(file.len() as u64))
+        } else if index.is_dir(&path) {
+            Ok(Metadata::dir())
+        } else {
+            Err(Error::from(ErrorKind::NotFound))
+        }
+    }
+
+    fn read_link_path(&self, path: &Path) -> Result<PathBuf> {
+        let index = read_index(&self.index);
+        let path = index::resolve_parent_links(path, Path::new("/"), ram_link(&index))?;
+        match index.get(&path) {
+            Some(RamNode::Symlink(target)) => Ok(target.clone()),
+            None if !index.is_dir(&path) => Err(Error::from(ErrorKind::NotFound)),
+            _ => Err(Error::new(ErrorKind::InvalidInput, "Not a symbolic link.")),
+        }
+    }
+}
+
+impl StoreMut for RamFs {
+    fn open_with_path(&self, path: &Path, options: &OpenOptions) -> Result<Self::File> {
+        if !options.is_write() {
+            if options.create || options.create_new {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Creating a file requires write access.",
+                ));
+            }
+            return Store::open_path(self, path);
+        }
+
+        let mut index = write_index(&self.index);
+        // writing to a link writes to its target.
+        let path = &ram_resolve(&index, path)?;
+        if index.is_dir(path) {
+            return Err(Error::other("Is a directory."));
+        }
+        let data = match index.get(path) {
+            Some(_) if options.create_new => return Err(Error::from(ErrorKind::AlreadyExists)),
+            Some(_) if options.truncate => Vec::new(),
+            Some(RamNode::File(file)) => file.to_vec(),
+            _ if options.create || options.create_new => {
+                index.insert(path, RamNode::File(Arc::from(Vec::new())));
+                Vec::new()
+            }
+            _ => return Err(Error::from(ErrorKind::NotFound)),
+        };
+
+        Ok(RamFile(RamFileInner::Write {
+            data: Cursor::new(data),
+            append: options.append,
+            // truncating an existing file modifies it, even if it's never written to.
+            dirty: options.truncate,
+            path: path.to_path_buf(),
+            index: Arc::clone(&self.index),
+        }))
+    }
+
+    fn remove_file_path(&self, path: &Path) -> Result<()> {
+        match write_index(&self.index).remove(path) {
+            Some(_) => Ok(()),
             None => Err(Error::from(ErrorKind::NotFound)),
         }
     }
 
-    fn entries_path(&self, path: &Path) -> Result<Entries> {
-        Ok(Entries::new(self.index.entries(path).map(|ent| {
-            Ok(Entry {
-                name: ent.name.to_os_string(),
-                kind: ent.kind,
-            })
-        })))
+    fn create_dir_path(&self, path: &Path) -> Result<()> {
+        let mut index = write_index(&self.index);
+        if index.contains(path) || index.is_dir(path) {
+            Err(Error::from(ErrorKind::AlreadyExists))
+        } else {
+            index.insert_dir(path);
+            Ok(())
+        }
+    }
+
+    fn rename_path(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut index = write_index(&self.index);
+        if index.is_dir(from) {
+            return Err(Error::other("Renaming directories is not supported."));
+        }
+        if index.is_dir(to) {
+            return Err(Error::other("Is a directory."));
+        }
+        match index.remove(from) {
+            Some(file) => {
+                index.insert(to, file);
+                Ok(())
+            }
+            None => Err(Error::from(ErrorKind::NotFound)),
+        }
+    }
+}
+
+impl Default for RamFs {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl RamFs {
     pub fn new() -> Self {
         Self {
-            index: index::Index::new(),
+            index: Arc::new(RwLock::new(index::Index::new())),
         }
     }
 
     pub fn clear(&mut self) {
-        self.index.clear();
+        write_index(&self.index).clear();
     }
 
-    pub fn rm<P: AsRef<Path>>(&mut self, path: P) -> Option<Rc<[u8]>> {
-        self.index.remove(path)
+    /// Removes a file or a symbolic link, returning the data of the file.
+    ///
+    /// Links are removed, not their targets.
+    pub fn rm<P: AsRef<Path>>(&mut self, path: P) -> Option<Arc<[u8]>> {
+        write_index(&self.index)
+            .remove(path)
+            .and_then(RamNode::into_file)
+    }
+
+    /// Removes a directory and everything in it, returning the removed files
+    /// (with paths relative to the directory).
+    pub fn rm_dir<P: AsRef<Path>>(&mut self, path: P) -> Option<Vec<(PathBuf, Arc<[u8]>)>> {
+        let removed = write_index(&self.index).remove_dir(path)?;
+        let files = removed
+            .into_iter()
+            .filter_map(|(path, node)| Some((path, node.into_file()?)))
+            .collect();
+        Some(files)
     }
 
     pub fn touch<P, F>(&mut self, path: P, file: F)
     where
         P: Into<PathBuf>,
-        F: Into<Rc<[u8]>>,
+        F: Into<Arc<[u8]>>,
+    {
+        write_index(&self.index).insert(path.into(), RamNode::File(file.into()));
+    }
+
+    /// Creates a whiteout that hides a file or directory from the lower layers
+    /// of an overlay.
+    ///
+    /// See the [`whiteout`] module.
+    ///
+    /// [`whiteout`]: ./whiteout/index.html
+    pub fn whiteout<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+        if let Some(name) = path.file_name() {
+            let marker = path.with_file_name(whiteout::whiteout_name(name));
+            self.touch(marker, Vec::new());
+        }
+    }
+
+    /// Makes a directory opaque, which hides its contents from the lower
+    /// layers of an overlay.
+    ///
+    /// See the [`whiteout`] module.
+    ///
+    /// [`whiteout`]: ./whiteout/index.html
+    pub fn opaque<P: AsRef<Path>>(&mut self, path: P) {
+        self.touch(path.as_ref().join(whiteout::OPAQUE), Vec::new());
+    }
+
+    /// Creates a symbolic link at `link` pointing to `target`.
+    ///
+    /// Relative targets are resolved against the directory of the link. The
+    /// target doesn't need to exist.
+    pub fn symlink<P, Q>(&mut self, target: P, link: Q)
+    where
+        P: Into<PathBuf>,
+        Q: Into<PathBuf>,
     {
-        self.index.insert(path.into(), file.into());
+        write_index(&self.index).insert(link.into(), RamNode::Symlink(target.into()));
     }
 
     pub fn index(self) -> Self {
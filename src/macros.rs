@@ -49,13 +49,6 @@ macro_rules! impl_file {
     }
 }
 
-macro_rules! store_entries {
-    ($self:ident, $path:expr, $head:ident,) => { $self.0.entries_path($path)? };
-    ($self:ident, $path:expr, $head:ident, $($tail:ident,)+) => {
-        $self.0.entries_path($path)?.chain(store_entries!($self, $path, $($tail,)+) )
-    }
-}
-
 macro_rules! store_tuples {
     ($head:ident,) => {};
     ($head:ident, $($tail:ident,)+) => {
@@ -70,26 +63,56 @@ macro_rules! store_tuples {
             #[allow(non_snake_case)]
             fn open_path(&self, path: &Path) -> io::Result<Self::File> {
                 let ($head, $($tail,)+) = self;
-                match $head.open_path(path) {
-                    Ok(file) => return Ok(file.into()),
-                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => {},
-                    Err(err) => return Err(err),
-                }
-                $(
-                match $tail.open_path(path) {
-                    Ok(file) => return Ok(file.into()),
-                    Err(ref err) if err.kind() == io::ErrorKind::NotFound => {},
-                    Err(err) => return Err(err),
+                $crate::whiteout::Lookup::new(path)
+                    .layer($head, |store, path| store.open_path(path).map(Into::into))
+                    $(.layer($tail, |store, path| store.open_path(path).map(Into::into)))+
+                    .finish()
+            }
+
+            #[allow(non_snake_case)]
+            fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+                let ($head, $($tail,)+) = self;
+                let mut layers = Vec::new();
+                // the layers below an opaque directory are skipped.
+                'layers: {
+                    let (entries, opaque) = $crate::whiteout::layer_entries($head, path)?;
+                    layers.push(entries);
+                    if opaque {
+                        break 'layers;
+                    }
+                    $(
+                    let (entries, opaque) = $crate::whiteout::layer_entries($tail, path)?;
+                    layers.push(entries);
+                    if opaque {
+                        break 'layers;
+                    }
+                    )+
                 }
-                )+
+                Ok(Entries::new($crate::whiteout::OverlayEntries::new(layers)))
+            }
 
-                Err(io::Error::from(io::ErrorKind::NotFound))
+            #[allow(non_snake_case)]
+            fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+                let ($head, $($tail,)+) = self;
+                $crate::whiteout::Lookup::new(path)
+                    .layer($head, |store, path| store.metadata_path(path))
+                    $(.layer($tail, |store, path| store.metadata_path(path)))+
+                    .finish()
             }
 
-            fn entries_path(&self, path: &Path) -> io::Result<Entries> {
-                // chain all elements from the tuple
-                let raw = store_entries!(self, path, $head, $($tail,)+);
-                Ok(Entries::new(TupleEntries::new(raw)))
+            #[allow(non_snake_case)]
+            fn read_link_path(&self, path: &Path) -> io::Result<std::path::PathBuf> {
+                let ($head, $($tail,)+) = self;
+                $crate::whiteout::Lookup::new(path)
+                    .layer($head, |store, path| store.read_link_path(path))
+                    $(.layer($tail, |store, path| store.read_link_path(path)))+
+                    .finish()
+            }
+
+            #[allow(non_snake_case)]
+            fn case_sensitive_path(&self, path: &Path) -> bool {
+                let ($head, $($tail,)+) = self;
+                $head.case_sensitive_path(path) $(&& $tail.case_sensitive_path(path))+
             }
         }
         store_tuples!($($tail,)+);
@@ -0,0 +1,114 @@
+//! This module contains memory-mapped files.
+//!
+//! Requires the `mmap` feature.
+//!
+//! # Safety
+//!
+//! Files are mapped with [`memmap2`], so modifying or truncating a file from
+//! outside of the process while it is mapped is undefined behaviour. Only map
+//! files that won't change, such as read-only assets.
+//!
+//! [`memmap2`]: https://docs.rs/memmap2
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+use crate::store::Store;
+use crate::{Entries, LocalFs, Metadata};
+
+/// Memory-mapped file.
+///
+/// The contents of the file can be accessed without copying them using the
+/// `as_bytes` method.
+pub struct MmapFile {
+    inner: Cursor<MmapSlice>,
+}
+
+// Region of a shared mapping.
+struct MmapSlice {
+    map: Arc<Mmap>,
+    start: usize,
+    len: usize,
+}
+
+impl AsRef<[u8]> for MmapSlice {
+    fn as_ref(&self) -> &[u8] {
+        &self.map[self.start..self.start + self.len]
+    }
+}
+
+impl MmapFile {
+    /// Maps the whole file into memory.
+    pub(crate) fn map(file: &fs::File) -> io::Result<Self> {
+        // Safety: see the module docs.
+        let map = unsafe { Mmap::map(file)? };
+        let len = map.len();
+        Ok(Self::slice(Arc::new(map), 0, len))
+    }
+
+    /// File backed by a region of an existing mapping.
+    pub(crate) fn slice(map: Arc<Mmap>, start: usize, len: usize) -> Self {
+        Self {
+            inner: Cursor::new(MmapSlice { map, start, len }),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.inner.get_ref().as_ref()
+    }
+}
+
+impl Read for MmapFile {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for MmapFile {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+/// Native file store that memory-maps the files it opens.
+///
+/// Created with [`LocalFs::mmap`].
+///
+/// [`LocalFs::mmap`]: ../struct.LocalFs.html#method.mmap
+pub struct MmapFs {
+    local: LocalFs,
+}
+
+impl MmapFs {
+    pub(crate) fn new(local: LocalFs) -> Self {
+        Self { local }
+    }
+}
+
+impl Store for MmapFs {
+    type File = MmapFile;
+
+    fn open_path(&self, path: &Path) -> io::Result<MmapFile> {
+        MmapFile::map(&self.local.open_path(path)?)
+    }
+
+    #[inline]
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+        self.local.entries_path(path)
+    }
+
+    #[inline]
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        self.local.metadata_path(path)
+    }
+
+    #[inline]
+    fn read_link_path(&self, path: &Path) -> io::Result<PathBuf> {
+        self.local.read_link_path(path)
+    }
+}
@@ -0,0 +1,296 @@
+//! This module contains a copy-on-write overlay filesystem.
+//!
+//! An [`OverlayFs`] combines a read-only lower layer (an archive, for example)
+//! with a writable upper layer. Reads prefer the upper layer, and every
+//! modification goes to the upper layer, so the lower layer is never modified:
+//!
+//! - Writing to a file of the lower layer copies it to the upper layer first.
+//! - Removing or renaming a file of the lower layer leaves a [whiteout] in the
+//!   upper layer, which hides it from then on.
+//!
+//! ```
+//! use mini_fs::prelude::*;
+//! use mini_fs::{OpenOptions, OverlayFs, RamFs};
+//! use std::io::Write;
+//!
+//! let mut base = RamFs::new();
+//! base.touch("config.ini", b"volume=5".to_vec());
+//! base.touch("save.dat", vec![]);
+//!
+//! let fs = OverlayFs::new(base, RamFs::new());
+//! fs.open_with("config.ini", OpenOptions::new().append(true))?
+//!     .write_all(b"\nfullscreen=1")?;
+//! fs.remove_file("save.dat")?;
+//!
+//! assert_eq!(1, fs.entries("")?.count());
+//! assert!(fs.lower().open("save.dat").is_ok());
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! [`OverlayFs`]: ./struct.OverlayFs.html
+//! [whiteout]: ../whiteout/index.html
+use std::io::{self, ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::store::{Entries, Metadata, OpenOptions, Store, StoreMut};
+use crate::whiteout::{self, Lookup, OverlayEntries};
+use crate::File;
+
+/// Copy-on-write filesystem with a read-only lower layer and a writable upper
+/// layer.
+///
+/// Listings merge the entries of both layers, like the tuple and `Vec`
+/// overlays.
+#[derive(Clone, Debug)]
+pub struct OverlayFs<L, U> {
+    lower: L,
+    upper: U,
+}
+
+impl<L, U> OverlayFs<L, U> {
+    /// Creates a new overlay. Files are only ever written to `upper`.
+    pub fn new(lower: L, upper: U) -> Self {
+        Self { lower, upper }
+    }
+
+    /// Gets a reference to the read-only lower layer.
+    pub fn lower(&self) -> &L {
+        &self.lower
+    }
+
+    /// Gets a reference to the writable upper layer.
+    pub fn upper(&self) -> &U {
+        &self.upper
+    }
+
+    /// Moves the lower and upper layers out of the overlay.
+    pub fn into_inner(self) -> (L, U) {
+        (self.lower, self.upper)
+    }
+}
+
+impl<L, U> OverlayFs<L, U>
+where
+    L: Store,
+    U: Store,
+{
+    // Returns the lower layer metadata of the path, unless the upper layer
+    // hides it.
+    fn lower_metadata(&self, path: &Path) -> Option<Metadata> {
+        if whiteout::hides(&self.upper, path) {
+            None
+        } else {
+            self.lower.metadata_path(path).ok()
+        }
+    }
+}
+
+impl<L, U> OverlayFs<L, U>
+where
+    L: Store,
+    U: StoreMut,
+    L::File: Into<File>,
+    U::File: Into<File>,
+{
+    // Creates the parent directories of the path in the upper layer, if they
+    // are directories of the overlay.
+    fn create_parents(&self, path: &Path) -> io::Result<()> {
+        let parent = match path.parent() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+        let mut dir = PathBuf::new();
+        for comp in parent.components() {
+            dir.push(comp);
+            if let Component::Normal(_) = comp {
+                if self.upper.metadata_path(&dir).is_ok() {
+                    continue;
+                }
+                match self.lower_metadata(&dir) {
+                    Some(ref meta) if meta.is_dir() => match self.upper.create_dir_path(&dir) {
+                        Err(err) if err.kind() != ErrorKind::AlreadyExists => return Err(err),
+                        _ => {}
+                    },
+                    // let the upper layer fail.
+                    _ => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Copies a file of the lower layer to the upper layer.
+    fn copy_up(&self, from: &Path, to: &Path, truncate: bool) -> io::Result<()> {
+        self.create_parents(to)?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        let mut file: File = self.upper.open_with_path(to, &options)?.into();
+        if !truncate {
+            let mut src: File = self.lower.open_path(from)?.into();
+            io::copy(&mut src, &mut file)?;
+        }
+        file.flush()
+    }
+
+    // Hides a path of the lower layer.
+    fn whiteout(&self, path: &Path) -> io::Result<()> {
+        let name = match path.file_name() {
+            Some(name) => whiteout::whiteout_name(name),
+            None => return Err(io::Error::new(ErrorKind::InvalidInput, "Invalid path.")),
+        };
+        let marker = path.with_file_name(name);
+        self.create_parents(&marker)?;
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        self.upper.open_with_path(&marker, &options).map(drop)
+    }
+}
+
+impl<L, U> Store for OverlayFs<L, U>
+where
+    L: Store,
+    U: Store,
+    L::File: Into<File>,
+    U::File: Into<File>,
+{
+    type File = File;
+
+    /// Opens the file from the upper layer, or from the lower layer if the
+    /// upper layer doesn't contain (or hide) it.
+    fn open_path(&self, path: &Path) -> io::Result<File> {
+        Lookup::new(path)
+            .layer(&self.upper, |store, path| {
+                store.open_path(path).map(Into::into)
+            })
+            .layer(&self.lower, |store, path| {
+                store.open_path(path).map(Into::into)
+            })
+            .finish()
+    }
+
+    /// Returns an iterator over the entries of both layers.
+    /// Skips duplicate entries, and the entries hidden by whiteouts.
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+        let mut layers = Vec::with_capacity(2);
+        let opaque = match whiteout::layer_entries(&self.upper, path) {
+            Ok((entries, opaque)) => {
+                layers.push(entries);
+                opaque
+            }
+            Err(ref err) if err.kind() == ErrorKind::NotFound => whiteout::hides(&self.upper, path),
+            Err(err) => return Err(err),
+        };
+        if !opaque {
+            match self.lower.entries_path(path) {
+                Ok(entries) => layers.push(entries),
+                Err(ref err) if err.kind() == ErrorKind::NotFound && !layers.is_empty() => {}
+                Err(err) => return Err(err),
+            }
+        }
+        if layers.is_empty() {
+            return Err(ErrorKind::NotFound.into());
+        }
+        Ok(Entries::new(OverlayEntries::new(layers)))
+    }
+
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        Lookup::new(path)
+            .layer(&self.upper, |store, path| store.metadata_path(path))
+            .layer(&self.lower, |store, path| store.metadata_path(path))
+            .finish()
+    }
+
+    fn read_link_path(&self, path: &Path) -> io::Result<PathBuf> {
+        Lookup::new(path)
+            .layer(&self.upper, |store, path| store.read_link_path(path))
+            .layer(&self.lower, |store, path| store.read_link_path(path))
+            .finish()
+    }
+
+    fn case_sensitive_path(&self, path: &Path) -> bool {
+        self.upper.case_sensitive_path(path) && self.lower.case_sensitive_path(path)
+    }
+}
+
+impl<L, U> StoreMut for OverlayFs<L, U>
+where
+    L: Store,
+    U: StoreMut,
+    L::File: Into<File>,
+    U::File: Into<File>,
+{
+    /// Opens a file with the given options.
+    ///
+    /// Files of the lower layer are copied to the upper layer before they are
+    /// opened for writing.
+    fn open_with_path(&self, path: &Path, options: &OpenOptions) -> io::Result<File> {
+        let creates = options.is_write() && (options.create || options.create_new);
+        if self.upper.metadata_path(path).is_ok() || self.lower_metadata(path).is_none() {
+            if creates {
+                self.create_parents(path)?;
+            }
+            return self.upper.open_with_path(path, options).map(Into::into);
+        }
+        // the file is only in the lower layer.
+        if !options.is_write() {
+            return self.lower.open_path(path).map(Into::into);
+        }
+        if options.create_new {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        self.copy_up(path, path, options.truncate)?;
+        self.upper.open_with_path(path, options).map(Into::into)
+    }
+
+    /// Removes a file from the upper layer, and hides it if the lower layer
+    /// contains it too.
+    fn remove_file_path(&self, path: &Path) -> io::Result<()> {
+        let lower = self.lower_metadata(path);
+        if self.upper.metadata_path(path).is_ok() {
+            self.upper.remove_file_path(path)?;
+        } else {
+            match lower {
+                Some(ref meta) if meta.is_dir() => return Err(io::Error::other("Is a directory.")),
+                Some(_) => {}
+                None => return Err(ErrorKind::NotFound.into()),
+            }
+        }
+        if lower.is_some() {
+            self.whiteout(path)?;
+        }
+        Ok(())
+    }
+
+    /// Creates a directory in the upper layer.
+    ///
+    /// A directory that replaces a removed directory of the lower layer
+    /// doesn't contain any of its files.
+    fn create_dir_path(&self, path: &Path) -> io::Result<()> {
+        if self.metadata_path(path).is_ok() {
+            return Err(ErrorKind::AlreadyExists.into());
+        }
+        self.create_parents(path)?;
+        self.upper.create_dir_path(path)
+    }
+
+    /// Renames a file, copying it to the upper layer if needed.
+    fn rename_path(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let lower = self.lower_metadata(from);
+        if self.upper.metadata_path(from).is_ok() {
+            self.create_parents(to)?;
+            self.upper.rename_path(from, to)?;
+        } else {
+            match lower {
+                Some(ref meta) if meta.is_dir() => {
+                    return Err(io::Error::other("Renaming directories is not supported."))
+                }
+                Some(_) => self.copy_up(from, to, false)?,
+                None => return Err(ErrorKind::NotFound.into()),
+            }
+        }
+        if lower.is_some() {
+            self.whiteout(from)?;
+        }
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
-use std::collections::btree_set::BTreeSet;
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::glob::Glob;
+use crate::walk::Walk;
+use crate::whiteout::{self, Lookup, OverlayEntries};
 
 /// File or directory entry.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -10,13 +14,67 @@ pub struct Entry {
     pub kind: EntryKind,
 }
 
+impl Entry {
+    // Name of the entry in its directory. Some stores, like `LocalFs`, return
+    // paths relative to their root as names.
+    pub(crate) fn file_name(&self) -> &OsStr {
+        let name = Path::new(&self.name);
+        name.file_name().unwrap_or(name.as_os_str())
+    }
+}
+
 /// Type of file entry.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum EntryKind {
     File,
     Dir,
-    /*TODO symlinks
-     *Sym, */
+    Symlink,
+}
+
+/// Metadata of a file or directory.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Metadata {
+    /// Size of the file in bytes (zero for directories).
+    pub len: u64,
+    pub kind: EntryKind,
+    /// Last modification time, if the store keeps track of it.
+    pub modified: Option<SystemTime>,
+    /// Unix permission bits, if the store keeps track of them.
+    pub mode: Option<u32>,
+}
+
+impl Metadata {
+    /// Metadata of a directory with no extra information.
+    pub fn dir() -> Self {
+        Self {
+            len: 0,
+            kind: EntryKind::Dir,
+            modified: None,
+            mode: None,
+        }
+    }
+
+    /// Metadata of a file of the given size with no extra information.
+    pub fn file(len: u64) -> Self {
+        Self {
+            len,
+            kind: EntryKind::File,
+            modified: None,
+            mode: None,
+        }
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == EntryKind::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == EntryKind::Symlink
+    }
 }
 
 /// Iterator of file entries.
@@ -51,24 +109,181 @@ pub trait Store {
     fn open_path(&self, path: &Path) -> io::Result<Self::File>;
 
     /// Returns an iterator over the files & directory entries in a given path.
-    fn entries_path(&self, _: &Path) -> io::Result<Entries> {
-        unimplemented!("entries_path is not implemented.")
+    fn entries_path(&self, _: &Path) -> io::Result<Entries<'_>> {
+        Err(io::Error::other("entries_path is not implemented."))
+    }
+
+    /// Returns the metadata of the file or directory in a given path.
+    fn metadata_path(&self, _: &Path) -> io::Result<Metadata> {
+        Err(io::Error::other("metadata_path is not implemented."))
+    }
+
+    /// Returns the target of the symbolic link in a given path.
+    fn read_link_path(&self, _: &Path) -> io::Result<PathBuf> {
+        Err(io::Error::other("read_link_path is not implemented."))
+    }
+
+    /// Returns false if the names in a given directory are compared in a
+    /// case-insensitive way, which is how [`glob`] patterns are matched then.
+    ///
+    /// [`glob`]: ./trait.StoreExt.html#method.glob
+    fn case_sensitive_path(&self, _: &Path) -> bool {
+        true
     }
 }
 
 /// Convenient methods on top of Store.
 pub trait StoreExt: Store {
-    fn entries<P: AsRef<Path>>(&self, path: P) -> io::Result<Entries> {
+    fn entries<P: AsRef<Path>>(&self, path: P) -> io::Result<Entries<'_>> {
         <Self as Store>::entries_path(self, &crate::index::normalize_path(path.as_ref()))
     }
 
     fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File> {
         <Self as Store>::open_path(self, &crate::index::normalize_path(path.as_ref()))
     }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
+        <Self as Store>::metadata_path(self, &crate::index::normalize_path(path.as_ref()))
+    }
+
+    fn read_link<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
+        <Self as Store>::read_link_path(self, &crate::index::normalize_path(path.as_ref()))
+    }
+
+    fn case_sensitive<P: AsRef<Path>>(&self, path: P) -> bool {
+        <Self as Store>::case_sensitive_path(self, &crate::index::normalize_path(path.as_ref()))
+    }
+
+    /// Returns an iterator over the entries of a directory and all of its
+    /// subdirectories.
+    fn walk<P: AsRef<Path>>(&self, path: P) -> Walk<'_, Self> {
+        Walk::new(
+            self,
+            crate::index::normalize_path(path.as_ref()).into_owned(),
+        )
+    }
+
+    /// Returns an iterator over the paths that match a glob pattern.
+    ///
+    /// Fails if the pattern is malformed. See the [`glob`] module for the
+    /// pattern syntax.
+    ///
+    /// [`glob`]: ./glob/index.html
+    fn glob(&self, pattern: &str) -> io::Result<Glob<'_, Self>> {
+        Glob::new(self, pattern)
+    }
 }
 
 impl<T: Store> StoreExt for T {}
 
+/// Options used to open a file from a [`StoreMut`].
+///
+/// Mirrors [`std::fs::OpenOptions`]. The fields are public so custom stores can
+/// inspect them.
+///
+/// [`StoreMut`]: ./trait.StoreMut.html
+/// [`std::fs::OpenOptions`]: https://doc.rust-lang.org/std/fs/struct.OpenOptions.html
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OpenOptions {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub truncate: bool,
+    pub create: bool,
+    pub create_new: bool,
+}
+
+impl OpenOptions {
+    /// Creates a blank set of options, with every option set to `false`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Returns true if the options allow modifying the file.
+    pub fn is_write(&self) -> bool {
+        self.write || self.append
+    }
+}
+
+/// File storage that can be modified.
+pub trait StoreMut: Store {
+    /// Opens a file with the given options, creating it if requested.
+    fn open_with_path(&self, path: &Path, options: &OpenOptions) -> io::Result<Self::File>;
+
+    fn remove_file_path(&self, path: &Path) -> io::Result<()>;
+
+    fn create_dir_path(&self, path: &Path) -> io::Result<()>;
+
+    fn rename_path(&self, from: &Path, to: &Path) -> io::Result<()>;
+}
+
+/// Convenient methods on top of StoreMut.
+pub trait StoreMutExt: StoreMut {
+    /// Opens a file in write-only mode, creating it if it doesn't exist, and
+    /// truncating it if it does.
+    fn create<P: AsRef<Path>>(&self, path: P) -> io::Result<Self::File> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        self.open_with(path, &options)
+    }
+
+    fn open_with<P: AsRef<Path>>(&self, path: P, options: &OpenOptions) -> io::Result<Self::File> {
+        <Self as StoreMut>::open_with_path(
+            self,
+            &crate::index::normalize_path(path.as_ref()),
+            options,
+        )
+    }
+
+    fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        <Self as StoreMut>::remove_file_path(self, &crate::index::normalize_path(path.as_ref()))
+    }
+
+    fn create_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        <Self as StoreMut>::create_dir_path(self, &crate::index::normalize_path(path.as_ref()))
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> io::Result<()> {
+        <Self as StoreMut>::rename_path(
+            self,
+            &crate::index::normalize_path(from.as_ref()),
+            &crate::index::normalize_path(to.as_ref()),
+        )
+    }
+}
+
+impl<T: StoreMut> StoreMutExt for T {}
+
 pub(crate) struct MapFile<S, F> {
     store: S,
     clo: F,
@@ -98,45 +313,112 @@ where
     }
 
     #[inline]
-    fn entries_path(&self, path: &Path) -> io::Result<Entries> {
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
         self.store.entries_path(path)
     }
-}
 
-// iterator + set to take care of repeating elements.
-// TODO consider other data structures for the set.
-struct TupleEntries<I> {
-    inner: I,
-    set: BTreeSet<OsString>,
-}
+    #[inline]
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        self.store.metadata_path(path)
+    }
 
-impl<I> TupleEntries<I> {
-    fn new(inner: I) -> Self {
-        Self {
-            inner,
-            set: BTreeSet::new(),
-        }
+    #[inline]
+    fn read_link_path(&self, path: &Path) -> io::Result<PathBuf> {
+        self.store.read_link_path(path)
+    }
+
+    #[inline]
+    fn case_sensitive_path(&self, path: &Path) -> bool {
+        self.store.case_sensitive_path(path)
     }
 }
 
-impl<I> Iterator for TupleEntries<I>
+impl<U, S, F> StoreMut for MapFile<S, F>
 where
-    I: Iterator<Item = io::Result<Entry>>,
+    S: StoreMut,
+    F: Fn(S::File) -> U,
 {
-    type Item = <I as Iterator>::Item;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.inner.next() {
-                None => return None,
-                Some(Err(e)) => return Some(Err(e)),
-                Some(Ok(file)) => {
-                    if self.set.insert(file.name.clone()) {
-                        return Some(Ok(file));
-                    }
-                }
-            }
+    #[inline]
+    fn open_with_path(&self, path: &Path, options: &OpenOptions) -> io::Result<Self::File> {
+        match self.store.open_with_path(path, options) {
+            Ok(file) => Ok((self.clo)(file)),
+            Err(err) => Err(err),
         }
     }
+
+    #[inline]
+    fn remove_file_path(&self, path: &Path) -> io::Result<()> {
+        self.store.remove_file_path(path)
+    }
+
+    #[inline]
+    fn create_dir_path(&self, path: &Path) -> io::Result<()> {
+        self.store.create_dir_path(path)
+    }
+
+    #[inline]
+    fn rename_path(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.store.rename_path(from, to)
+    }
+}
+
+// Wraps a store so it can be used where a StoreMut is expected.
+// Any attempt to modify it fails with a PermissionDenied error.
+pub(crate) struct ReadOnly<S>(pub(crate) S);
+
+impl<S: Store> Store for ReadOnly<S> {
+    type File = S::File;
+
+    #[inline]
+    fn open_path(&self, path: &Path) -> io::Result<Self::File> {
+        self.0.open_path(path)
+    }
+
+    #[inline]
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+        self.0.entries_path(path)
+    }
+
+    #[inline]
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        self.0.metadata_path(path)
+    }
+
+    #[inline]
+    fn read_link_path(&self, path: &Path) -> io::Result<PathBuf> {
+        self.0.read_link_path(path)
+    }
+
+    #[inline]
+    fn case_sensitive_path(&self, path: &Path) -> bool {
+        self.0.case_sensitive_path(path)
+    }
+}
+
+impl<S: Store> StoreMut for ReadOnly<S> {
+    fn open_with_path(&self, path: &Path, options: &OpenOptions) -> io::Result<Self::File> {
+        if options.is_write() || options.create || options.create_new {
+            Err(read_only())
+        } else {
+            self.0.open_path(path)
+        }
+    }
+
+    fn remove_file_path(&self, _: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn create_dir_path(&self, _: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+
+    fn rename_path(&self, _: &Path, _: &Path) -> io::Result<()> {
+        Err(read_only())
+    }
+}
+
+fn read_only() -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, "Read-only store.")
 }
 
 // Implement tuples of up to 11 elements (12 or more looks bad on the rustdoc)
@@ -152,63 +434,48 @@ where
 
     /// Opens the file identified by path.
     fn open_path(&self, path: &Path) -> io::Result<Self::File> {
-        for store in self {
-            match store.open_path(&path) {
-                Ok(file) => return Ok(file),
-                Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
-                Err(err) => return Err(err),
-            }
-        }
-        return Err(io::ErrorKind::NotFound.into());
+        self.iter()
+            .fold(Lookup::new(path), |lookup, store| {
+                lookup.layer(store, |store, path| store.open_path(path))
+            })
+            .finish()
     }
 
     /// Returns an iterator over the entries.
-    /// Skips duplicate entries.
-    fn entries_path(&self, path: &Path) -> io::Result<Entries> {
-        let mut iterators = Vec::with_capacity(self.capacity());
-        for store in self.iter() {
-            iterators.push(store.entries_path(path)?);
+    /// Skips duplicate entries, and the entries hidden by whiteouts.
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+        let mut layers = Vec::with_capacity(self.len());
+        for store in self {
+            let (entries, opaque) = whiteout::layer_entries(store, path)?;
+            layers.push(entries);
+            // the layers below an opaque directory are skipped.
+            if opaque {
+                break;
+            }
         }
-        Ok(Entries::new(VecEntries {
-            inner: iterators,
-            set: BTreeSet::new(),
-        }))
+        Ok(Entries::new(OverlayEntries::new(layers)))
     }
-}
 
-/// Iterator over the entries of the inner stores that skips duplicates.
-struct VecEntries<I> {
-    /// Inner iterators.
-    inner: Vec<I>,
-    /// Set identifying the entries that have been returned.
-    /// Used to skip duplicates.
-    set: BTreeSet<OsString>,
-}
+    /// Returns the metadata from the first store that contains the path.
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        self.iter()
+            .fold(Lookup::new(path), |lookup, store| {
+                lookup.layer(store, |store, path| store.metadata_path(path))
+            })
+            .finish()
+    }
 
-impl<I> Iterator for VecEntries<I>
-where
-    I: Iterator<Item = io::Result<Entry>>,
-{
-    type Item = I::Item;
+    /// Reads the link from the first store that contains the path.
+    fn read_link_path(&self, path: &Path) -> io::Result<PathBuf> {
+        self.iter()
+            .fold(Lookup::new(path), |lookup, store| {
+                lookup.layer(store, |store, path| store.read_link_path(path))
+            })
+            .finish()
+    }
 
-    /// Gets the next entry result or None.
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if self.inner.len() == 0 {
-                return None; // no more entries
-            }
-            while let Some(result) = self.inner[0].next() {
-                match result {
-                    Err(err) => return Some(Err(err)),
-                    Ok(entry) => {
-                        // skip duplicate entries
-                        if self.set.insert(entry.name.clone()) {
-                            return Some(Ok(entry));
-                        }
-                    }
-                }
-            }
-            self.inner.remove(0); // iterator is done, try next
-        }
+    /// Names are case-insensitive if they are in any of the stores.
+    fn case_sensitive_path(&self, path: &Path) -> bool {
+        self.iter().all(|store| store.case_sensitive_path(path))
     }
 }
@@ -0,0 +1,120 @@
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+use flate2::Crc;
+
+use crate::window::seek_position;
+
+/// Opens a reader of the whole stream, at the given offset or before it.
+/// Returns the reader and the offset it starts at.
+pub(crate) type OpenFn = Box<dyn Fn(u64) -> io::Result<(Box<dyn Read + Send>, u64)> + Send + Sync>;
+
+// size of the buffer used to skip data.
+const SKIP_SIZE: usize = 8 * 1024;
+
+/// Seekable view over a region of a stream that can only be read forwards,
+/// such as the output of a decompressor.
+///
+/// Seeking only moves the position. Reads skip data to get to the position,
+/// or reopen the stream when the position is behind the reader.
+pub(crate) struct Stream {
+    open: OpenFn,
+    reader: Option<Box<dyn Read + Send>>,
+    // offset of the reader in the stream.
+    offset: u64,
+    start: u64,
+    len: u64,
+    pos: u64,
+    // expected checksum of the region, and the checksum of the data read so
+    // far, if the reader started at the beginning of the region.
+    crc: Option<(u32, Option<Crc>)>,
+}
+
+impl Stream {
+    pub(crate) fn new(open: OpenFn, start: u64, len: u64) -> Self {
+        Self {
+            open,
+            reader: None,
+            offset: 0,
+            start,
+            len,
+            pos: 0,
+            crc: None,
+        }
+    }
+
+    /// Check the CRC-32 of the region when it's read up to the end.
+    #[cfg_attr(not(feature = "zip"), allow(dead_code))]
+    pub(crate) fn crc32(mut self, crc: u32) -> Self {
+        self.crc = Some((crc, None));
+        self
+    }
+
+    fn reopen(&mut self, offset: u64) -> io::Result<()> {
+        let (reader, offset) = (self.open)(offset)?;
+        self.reader = Some(reader);
+        self.offset = offset;
+        if let Some((_, ref mut hasher)) = self.crc {
+            *hasher = if offset <= self.start {
+                Some(Crc::new())
+            } else {
+                None
+            };
+        }
+        Ok(())
+    }
+
+    // Reads from the current offset of the reader, into a non-empty buffer
+    // that doesn't go past the end of the region.
+    fn read_inner(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = match self.reader {
+            Some(ref mut reader) => reader.read(buf)?,
+            None => 0,
+        };
+        if read == 0 {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "Unexpected end of stream.",
+            ));
+        }
+        let end = self.start + self.len;
+        if let Some((expected, Some(ref mut hasher))) = self.crc {
+            let skip = self.start.saturating_sub(self.offset).min(read as u64);
+            hasher.update(&buf[skip as usize..read]);
+            if self.offset + read as u64 == end && hasher.sum() != expected {
+                return Err(io::Error::new(ErrorKind::InvalidData, "Invalid checksum."));
+            }
+        }
+        self.offset += read as u64;
+        Ok(read)
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.len || buf.is_empty() {
+            return Ok(0);
+        }
+        let target = self.start + self.pos;
+        if self.reader.is_none() || target < self.offset {
+            self.reopen(target)?;
+        }
+        if self.offset < target {
+            let mut skip = vec![0; SKIP_SIZE];
+            while self.offset < target {
+                let max = (target - self.offset).min(SKIP_SIZE as u64) as usize;
+                self.read_inner(&mut skip[..max])?;
+            }
+        }
+        let max = (self.len - self.pos).min(buf.len() as u64) as usize;
+        let read = self.read_inner(&mut buf[..max])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for Stream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_position(pos, self.pos, self.len)?;
+        Ok(self.pos)
+    }
+}
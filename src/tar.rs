@@ -1,14 +1,21 @@
-use std::cell::{Cell, RefCell};
 use std::fs;
-use std::io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{self, BufReader, Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use flate2::read::GzDecoder;
-use tar_::Archive;
+use flate2::write::GzEncoder;
+use tar_::{Archive, Builder, EntryType, Header};
 
-use crate::index::Index;
+use crate::gzip::{self, Checkpoint, GzReader};
+use crate::index::{self, Index};
 use crate::store::Store;
-use crate::{Entries, Entry};
+use crate::stream::{OpenFn, Stream};
+use crate::sync::lock;
+use crate::walk::Walk;
+use crate::window::{SharedReader, Window};
+use crate::{Entries, Entry, EntryKind, Metadata};
 
 /// Tar archive.
 ///
@@ -16,27 +23,126 @@ use crate::{Entries, Entry};
 ///
 /// When used with a `std::fs::File`, the file will remain open for the lifetime
 /// of the Tar.
+///
+/// Files are read into memory when they are opened. Once the archive is
+/// indexed, members of uncompressed archives are read directly from their
+/// offset. Use [`stream_files`] to read files from the archive as they are
+/// read instead.
+///
+/// Gzipped archives are always supported. Archives compressed with zstd, xz or
+/// bzip2 need the `zstd`, `xz` or `bzip2` features. The compression is detected
+/// from the first bytes of the archive, regardless of the file name.
+///
+/// Reading a member of a gzipped archive requires decompressing the archive up
+/// to the member. Use the [`checkpoints`] method to resume decompression from
+/// a point closer to the member (or to the position in the member) instead.
+/// Only the first member of a gzip file is read, so archives made by
+/// concatenating several gzip files are cut short after the first one. The
+/// checksum at the end of the archive is verified when the archive is indexed.
+///
+/// The contents of the archive are indexed the first time they are listed,
+/// unless the [`index`] method has been called before.
+///
+/// Symbolic links are followed when files are opened, and hard links are
+/// opened as the file they link to. Following links requires the index, so the
+/// archive is also indexed when a path goes through a link.
+///
+/// [`stream_files`]: #method.stream_files
+/// [`checkpoints`]: #method.checkpoints
+/// [`index`]: #method.index
 pub struct TarFs<F: Read + Seek> {
-    gzip: Cell<bool>,
-    inner: RefCell<F>,
-    index: Option<Index<SeekFrom>>,
+    // Compression of the archive, detected on the first read.
+    compression: OnceLock<Compression>,
+    inner: Arc<Mutex<F>>,
+    index: OnceLock<Index<TarEntry>>,
+    checkpoint_interval: Option<u64>,
+    // Shared with the files that resume decompression from them.
+    checkpoints: Arc<Mutex<Vec<Checkpoint>>>,
+    max_buffered: Option<u64>,
+    // The inner reader, shared with the files that read from it. Only set by
+    // `stream_files`, which requires the reader to be `Send + 'static`.
+    stream: Option<SharedReader>,
+}
+
+// Location and metadata of a member of the archive.
+#[derive(Debug, Clone)]
+struct TarEntry {
+    // Offset of the data, relative to the decompressed stream for compressed
+    // archives.
+    offset: u64,
+    size: u64,
+    mtime: Option<u64>,
+    mode: Option<u32>,
+    // Target of symbolic links.
+    link: Option<PathBuf>,
+}
+
+impl TarEntry {
+    fn new<R: Read>(entry: &tar_::Entry<'_, R>) -> Self {
+        let header = entry.header();
+        Self {
+            offset: entry.raw_file_position(),
+            size: entry.size(),
+            mtime: header.mtime().ok(),
+            mode: header.mode().ok(),
+            link: None,
+        }
+    }
+
+    fn metadata(&self, kind: EntryKind) -> Metadata {
+        Metadata {
+            len: if kind == EntryKind::Dir { 0 } else { self.size },
+            kind,
+            modified: self
+                .mtime
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            mode: self.mode,
+        }
+    }
 }
 
 /// Entry in the Tar archive.
 pub struct TarFsFile {
-    inner: Cursor<Box<[u8]>>,
+    inner: TarFsFileInner,
+}
+
+enum TarFsFileInner {
+    Buffered(Cursor<Box<[u8]>>),
+    Window(BufReader<Window>),
+    Stream(Stream),
+}
+
+impl TarFsFile {
+    /// Returns the contents of the file if they were read into memory, which
+    /// is always the case unless [`TarFs::stream_files`] is used.
+    ///
+    /// [`TarFs::stream_files`]: ./struct.TarFs.html#method.stream_files
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.inner {
+            TarFsFileInner::Buffered(ref file) => Some(file.get_ref()),
+            TarFsFileInner::Window(_) | TarFsFileInner::Stream(_) => None,
+        }
+    }
 }
 
 impl Read for TarFsFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
+        match self.inner {
+            TarFsFileInner::Buffered(ref mut file) => file.read(buf),
+            TarFsFileInner::Window(ref mut file) => file.read(buf),
+            TarFsFileInner::Stream(ref mut file) => file.read(buf),
+        }
     }
 }
 
 impl Seek for TarFsFile {
     #[inline]
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.inner.seek(pos)
+        match self.inner {
+            TarFsFileInner::Buffered(ref mut file) => file.seek(pos),
+            TarFsFileInner::Window(ref mut file) => file.seek(pos),
+            TarFsFileInner::Stream(ref mut file) => file.seek(pos),
+        }
     }
 }
 
@@ -44,36 +150,68 @@ impl<T: Read + Seek> Store for TarFs<T> {
     type File = TarFsFile;
 
     fn open_path(&self, path: &Path) -> io::Result<Self::File> {
-        if self.gzip.get() {
-            let mut file = self.inner.borrow_mut();
-            file.seek(SeekFrom::Start(0))?;
-            self.open_read(path, GzDecoder::new(&mut *file))
-        } else {
-            let mut file = self.inner.borrow_mut();
-            file.seek(SeekFrom::Start(0))?;
-            match self.open_read(path, &mut *file) {
-                Ok(entry) => Ok(entry),
-                Err(ref e) if e.kind() == ErrorKind::NotFound => {
-                    Err(io::Error::from(ErrorKind::NotFound))
+        let entry = match self.index.get() {
+            Some(index) => resolve_entry(index, path)?,
+            None => match self.read_archive(|read| find_read(path, read, self.buffered_len()))? {
+                // compressed data is read while looking for the member, to avoid
+                // decompressing the archive twice.
+                Some((_, Some(data))) => {
+                    return Ok(TarFsFile {
+                        inner: TarFsFileInner::Buffered(Cursor::new(data.into())),
+                    })
                 }
-                Err(_) => {
-                    self.gzip.set(true);
-                    drop(file);
-                    self.open_path(path)
-                }
-            }
-        }
+                Some((entry, None)) => entry,
+                None => resolve_entry(self.get_index()?, path)?,
+            },
+        };
+        self.open_entry(entry)
+    }
+
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+        let idx = self.get_index()?;
+        Ok(Entries::new(idx.entries(path).map(|ent| {
+            let name = ent.name.to_os_string();
+            let kind = match ent.meta {
+                Some(&TarEntry { link: Some(_), .. }) => EntryKind::Symlink,
+                _ => ent.kind,
+            };
+            Ok(Entry { name, kind })
+        })))
     }
 
-    fn entries_path(&self, path: &Path) -> io::Result<Entries> {
-        if let Some(ref idx) = self.index {
-            Ok(Entries::new(idx.entries(path).map(|ent| {
-                let name = ent.name.to_os_string();
-                let kind = ent.kind;
-                Ok(Entry { name, kind })
-            })))
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        let index = match self.index.get() {
+            Some(index) => index,
+            None => match self.read_archive(|read| metadata_read(path, read))? {
+                Some(meta) => return Ok(meta),
+                None => self.get_index()?,
+            },
+        };
+        let path = index::resolve_links(path, Path::new(""), tar_link(index))?;
+        if let Some(entry) = index.get(&path) {
+            Ok(entry.metadata(EntryKind::File))
+        } else if let Some(entry) = index.dir_meta(&path) {
+            Ok(entry.metadata(EntryKind::Dir))
+        } else if index.is_dir(&path) {
+            Ok(Metadata::dir())
         } else {
-            panic!("You have to call the `Zip::index` method on this zip archive before you can list its entries.")
+            Err(io::Error::from(ErrorKind::NotFound))
+        }
+    }
+
+    fn read_link_path(&self, path: &Path) -> io::Result<PathBuf> {
+        let index = self.get_index()?;
+        let path = index::resolve_parent_links(path, Path::new(""), tar_link(index))?;
+        match index.get(&path) {
+            Some(&TarEntry {
+                link: Some(ref target),
+                ..
+            }) => Ok(target.clone()),
+            None if !index.is_dir(&path) => Err(io::Error::from(ErrorKind::NotFound)),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Not a symbolic link.",
+            )),
         }
     }
 }
@@ -93,32 +231,617 @@ impl TarFs<fs::File> {
 impl<T: Read + Seek> TarFs<T> {
     pub fn new(inner: T) -> Self {
         Self {
-            inner: RefCell::new(inner),
-            gzip: Cell::new(false),
-            index: None,
+            inner: Arc::new(Mutex::new(inner)),
+            compression: OnceLock::new(),
+            index: OnceLock::new(),
+            checkpoint_interval: None,
+            checkpoints: Arc::new(Mutex::new(Vec::new())),
+            max_buffered: None,
+            stream: None,
         }
     }
 
-    fn open_read<R: Read>(&self, path: &Path, read: R) -> io::Result<TarFsFile> {
-        let mut archive = Archive::new(read);
-        for entry in archive.entries()? {
-            let mut entry = entry?;
-            if path == entry.path()? {
-                let mut data = Vec::new();
-                entry.read_to_end(&mut data)?;
-                return Ok(TarFsFile {
-                    inner: Cursor::new(data.into()),
-                });
+    /// Record a checkpoint every `interval` bytes of compressed data when
+    /// reading a gzipped archive.
+    ///
+    /// Checkpoints are recorded as the archive is read (for example, when it is
+    /// indexed), and files are opened by resuming decompression from the
+    /// nearest checkpoint before their data. Each checkpoint keeps a copy of
+    /// the decompressor state, which takes around 45KiB of memory.
+    ///
+    /// Has no effect on uncompressed archives.
+    pub fn checkpoints(mut self, interval: u64) -> Self {
+        self.checkpoint_interval = Some(interval);
+        self
+    }
+
+    /// Decompress the members of compressed archives of up to `max_len` bytes
+    /// into memory when they are opened, instead of decompressing them as they
+    /// are read.
+    ///
+    /// Only has an effect on the compressed archives of a store that
+    /// [streams its files].
+    ///
+    /// [streams its files]: #method.stream_files
+    pub fn buffer_small_files(mut self, max_len: u64) -> Self {
+        self.max_buffered = Some(max_len);
+        self
+    }
+
+    // Files up to this size are read into memory while the archive is scanned
+    // for them.
+    fn buffered_len(&self) -> Option<u64> {
+        match self.stream {
+            None => Some(u64::MAX),
+            Some(_) if self.is_compressed() => self.max_buffered,
+            Some(_) => None,
+        }
+    }
+
+    fn is_compressed(&self) -> bool {
+        matches!(self.compression.get(), Some(c) if *c != Compression::None)
+    }
+
+    // The compression is detected from the magic bytes at the start of the
+    // file the first time the archive is read.
+    fn compression(&self, file: &mut T) -> io::Result<Compression> {
+        match self.compression.get() {
+            Some(compression) => Ok(*compression),
+            None => {
+                file.seek(SeekFrom::Start(0))?;
+                let compression = detect_compression(file)?;
+                Ok(*self.compression.get_or_init(|| compression))
             }
         }
-        Err(io::Error::from(ErrorKind::NotFound))
+    }
+
+    // Runs the closure over the (possibly compressed) contents of the archive.
+    fn read_archive<F, R>(&self, f: F) -> io::Result<R>
+    where
+        F: Fn(&mut dyn Read) -> io::Result<R>,
+    {
+        let mut file = lock(&self.inner);
+        let compression = self.compression(&mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        match compression {
+            Compression::None => f(&mut *file),
+            Compression::Gzip => self.read_gzip(&mut *file, f),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => f(&mut zstd_::Decoder::new(&mut *file)?),
+            #[cfg(feature = "xz")]
+            Compression::Xz => f(&mut xz2::read::XzDecoder::new_multi_decoder(&mut *file)),
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => f(&mut bzip2_::read::MultiBzDecoder::new(&mut *file)),
+        }
+    }
+
+    // Runs the closure over the decompressed archive, recording checkpoints if
+    // they have been enabled.
+    fn read_gzip<F, R>(&self, file: &mut T, f: F) -> io::Result<R>
+    where
+        F: Fn(&mut dyn Read) -> io::Result<R>,
+    {
+        match self.checkpoint_interval {
+            Some(interval) => {
+                // the archive lock is held, so nothing else records checkpoints
+                // in the meantime.
+                let checkpoints = std::mem::take(&mut *lock(&self.checkpoints));
+                let mut gz = GzReader::new(file).record(interval, checkpoints);
+                let res = f(&mut gz);
+                *lock(&self.checkpoints) = gz.into_checkpoints();
+                res
+            }
+            None => f(&mut GzDecoder::new(file)),
+        }
+    }
+
+    // Reads `size` bytes of the (decompressed) archive, starting at `offset`.
+    //
+    // Only gzipped archives have checkpoints to resume from.
+    fn read_data(&self, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+        if !self.is_compressed() {
+            let mut file = lock(&self.inner);
+            file.seek(SeekFrom::Start(offset))?;
+            return read_range(&mut *file, 0, size);
+        }
+        let checkpoint = gzip::nearest(&lock(&self.checkpoints), offset).cloned();
+        match checkpoint {
+            Some(checkpoint) => {
+                let mut file = lock(&self.inner);
+                file.seek(SeekFrom::Start(checkpoint.in_offset()))?;
+                let mut gz = GzReader::resume(&mut *file, &checkpoint);
+                read_range(&mut gz, offset - checkpoint.out_offset(), size)
+            }
+            None => self.read_archive(|read| read_range(read, offset, size)),
+        }
     }
 
     /// Index the contents of the archive.
     ///
-    /// Having an index allows you to list the contents of the archive using the
-    /// entries_path and entries methods.
+    /// The index is used to list the contents of the archive using the
+    /// entries_path and entries methods. It is built on the first listing if
+    /// this method isn't called. Once the archive is indexed, files of
+    /// uncompressed archives are opened without having to scan the archive.
+    ///
+    /// Indexing an uncompressed archive only reads the headers of its members.
+    /// Compressed archives are decompressed from start to end.
     pub fn index(self) -> io::Result<Self> {
-        unimplemented!("TarFs indexing hasn't been implemented yet.");
+        self.get_index()?;
+        Ok(self)
+    }
+
+    fn get_index(&self) -> io::Result<&Index<TarEntry>> {
+        if let Some(index) = self.index.get() {
+            return Ok(index);
+        }
+        let mut file = lock(&self.inner);
+        if self.compression(&mut file)? == Compression::None {
+            // the data of the members is skipped by seeking over it.
+            file.seek(SeekFrom::Start(0))?;
+            let index = index_read(Archive::new(&mut *file).entries_with_seek()?)?;
+            return Ok(self.index.get_or_init(|| index));
+        }
+        drop(file);
+        let index = self.read_archive(|read| {
+            let index = index_read(Archive::new(&mut *read).entries()?)?;
+            // read the padding after the last member too, so the end of the
+            // compressed stream (and its checksum) is checked.
+            io::copy(read, &mut io::sink())?;
+            Ok(index)
+        })?;
+        Ok(self.index.get_or_init(|| index))
+    }
+
+    fn open_entry(&self, entry: TarEntry) -> io::Result<TarFsFile> {
+        let compression = self.compression.get().copied().unwrap_or_default();
+        let buffered = self.max_buffered.is_some_and(|max| entry.size <= max);
+        let inner = match self.stream {
+            Some(ref reader) if compression == Compression::None => {
+                let window = Window::new(Arc::clone(reader), entry.offset, entry.size);
+                TarFsFileInner::Window(BufReader::new(window))
+            }
+            Some(ref reader) if !buffered => {
+                let reader = Arc::clone(reader);
+                TarFsFileInner::Stream(self.stream_entry(reader, compression, &entry))
+            }
+            _ => {
+                let data = self.read_data(entry.offset, entry.size)?;
+                TarFsFileInner::Buffered(Cursor::new(data.into()))
+            }
+        };
+        Ok(TarFsFile { inner })
+    }
+
+    // Decompresses the member as it's read, resuming from the checkpoints of
+    // gzipped archives.
+    fn stream_entry(
+        &self,
+        reader: SharedReader,
+        compression: Compression,
+        entry: &TarEntry,
+    ) -> Stream {
+        let checkpoints = Arc::clone(&self.checkpoints);
+        let open: OpenFn = Box::new(move |offset| {
+            let window = |start| Window::new(Arc::clone(&reader), start, u64::MAX - start);
+            if compression == Compression::Gzip {
+                if let Some(checkpoint) = gzip::nearest(&lock(&checkpoints), offset) {
+                    let gz = GzReader::resume(window(checkpoint.in_offset()), checkpoint);
+                    return Ok((Box::new(gz), checkpoint.out_offset()));
+                }
+            }
+            Ok((decoder(compression, window(0))?, 0))
+        });
+        Stream::new(open, entry.offset, entry.size)
+    }
+}
+
+impl<T: Read + Seek + Send + 'static> TarFs<T> {
+    /// Read files from the archive as they are read, instead of reading them
+    /// into memory when they are opened.
+    ///
+    /// Files of uncompressed archives read their data directly from the
+    /// underlying reader. Members of compressed archives are decompressed as
+    /// they are read, and seeking backwards in them restarts decompression
+    /// (from the nearest checkpoint, if there are any). Use
+    /// [`buffer_small_files`] to keep decompressing small members into memory.
+    ///
+    /// The files keep a handle to the reader, which is why it has to be
+    /// `Send + 'static`.
+    ///
+    /// [`buffer_small_files`]: #method.buffer_small_files
+    pub fn stream_files(mut self) -> Self {
+        let reader: SharedReader = self.inner.clone();
+        self.stream = Some(reader);
+        self
+    }
+}
+
+/// Compression of a tar archive.
+///
+/// [`TarFs`] detects the compression of the archives it reads. The compression
+/// of the archives written by a [`TarPacker`] is chosen with its
+/// [`compression`] method.
+///
+/// [`TarFs`]: ./struct.TarFs.html
+/// [`compression`]: ./struct.TarPacker.html#method.compression
+///
+/// [`TarPacker`]: ./struct.TarPacker.html
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Compression {
+    /// Plain tar archive.
+    #[default]
+    None,
+    /// Gzipped tar archive (`.tar.gz`).
+    Gzip,
+    /// Zstd compressed tar archive (`.tar.zst`).
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// Xz compressed tar archive (`.tar.xz`).
+    #[cfg(feature = "xz")]
+    Xz,
+    /// Bzip2 compressed tar archive (`.tar.bz2`).
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+}
+
+/// Writes the files of a store into a tar archive.
+///
+/// Entries are written in the order of their paths, with their modification
+/// times and permissions when the store keeps track of them, so the same files
+/// always produce the same archive. Symbolic links are stored as links.
+///
+/// ```
+/// use mini_fs::tar::{Compression, TarPacker};
+/// use mini_fs::RamFs;
+/// use std::time::UNIX_EPOCH;
+///
+/// let mut ram = RamFs::new();
+/// ram.touch("gfx/a.png", vec![0; 64]);
+/// ram.touch("sfx/b.ogg", vec![0; 64]);
+///
+/// let tar_gz = TarPacker::new()
+///     .compression(Compression::Gzip)
+///     .modified(UNIX_EPOCH)
+///     .pack(&ram, "", Vec::new())?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TarPacker {
+    compression: Compression,
+    modified: Option<SystemTime>,
+}
+
+impl TarPacker {
+    /// Creates a packer that writes uncompressed archives.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Use the same modification time for every entry, instead of the times
+    /// from the store.
+    ///
+    /// Entries without a modification time use the Unix epoch otherwise.
+    pub fn modified(mut self, time: SystemTime) -> Self {
+        self.modified = Some(time);
+        self
+    }
+
+    /// Writes the contents of a directory of the store into a tar archive, and
+    /// returns the writer.
+    ///
+    /// Paths in the archive are relative to `root`. Stores that don't implement
+    /// `metadata_path` can be packed too, without modification times or
+    /// permissions.
+    pub fn pack<S, P, W>(&self, store: &S, root: P, writer: W) -> io::Result<W>
+    where
+        S: Store + ?Sized,
+        S::File: Read,
+        P: AsRef<Path>,
+        W: Write,
+    {
+        let root = index::normalize_path(root.as_ref());
+        match self.compression {
+            Compression::None => self.write_tar(store, &root, writer),
+            Compression::Gzip => {
+                let gz = GzEncoder::new(writer, flate2::Compression::default());
+                self.write_tar(store, &root, gz)?.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let zstd = zstd_::Encoder::new(writer, 0)?;
+                self.write_tar(store, &root, zstd)?.finish()
+            }
+            #[cfg(feature = "xz")]
+            Compression::Xz => {
+                let xz = xz2::write::XzEncoder::new(writer, 6);
+                self.write_tar(store, &root, xz)?.finish()
+            }
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 => {
+                let bz = bzip2_::write::BzEncoder::new(writer, bzip2_::Compression::default());
+                self.write_tar(store, &root, bz)?.finish()
+            }
+        }
+    }
+
+    fn write_tar<S, W>(&self, store: &S, root: &Path, writer: W) -> io::Result<W>
+    where
+        S: Store + ?Sized,
+        S::File: Read,
+        W: Write,
+    {
+        let mut builder = Builder::new(writer);
+        let walk = Walk::new(store, root.to_path_buf()).sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in walk {
+            let entry = entry?;
+            let path = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+            let mut header = Header::new_gnu();
+            let modified = match entry.kind {
+                // the metadata of a link is the metadata of its target.
+                EntryKind::Symlink => self.modified,
+                _ => {
+                    let meta = entry.metadata(store)?;
+                    header.set_size(meta.len);
+                    header.set_mode(meta.mode.unwrap_or(match entry.kind {
+                        EntryKind::Dir => 0o755,
+                        _ => 0o644,
+                    }));
+                    self.modified.or(meta.modified)
+                }
+            };
+            let mtime = modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+            header.set_mtime(mtime.map_or(0, |time| time.as_secs()));
+            match entry.kind {
+                EntryKind::File => {
+                    header.set_entry_type(EntryType::Regular);
+                    let mut file = store.open_path(&entry.path)?;
+                    if header.size()? == 0 {
+                        // stores without metadata don't tell the size, so the
+                        // file is read to find it out.
+                        let mut data = Vec::new();
+                        file.read_to_end(&mut data)?;
+                        header.set_size(data.len() as u64);
+                        builder.append_data(&mut header, path, &data[..])?;
+                    } else {
+                        builder.append_data(&mut header, path, file)?;
+                    }
+                }
+                EntryKind::Dir => {
+                    header.set_entry_type(EntryType::Directory);
+                    header.set_size(0);
+                    builder.append_data(&mut header, path, io::empty())?;
+                }
+                EntryKind::Symlink => {
+                    header.set_entry_type(EntryType::Symlink);
+                    header.set_size(0);
+                    header.set_mode(0o777);
+                    let target = store.read_link_path(&entry.path)?;
+                    builder.append_link(&mut header, path, target)?;
+                }
+            }
+        }
+        builder.into_inner()
+    }
+}
+
+/// Writes the contents of a directory of the store into a tar archive, with
+/// the given compression.
+///
+/// See [`TarPacker`] for more options.
+///
+/// [`TarPacker`]: ./struct.TarPacker.html
+pub fn pack_tar<S, P, W>(store: &S, root: P, writer: W, compression: Compression) -> io::Result<W>
+where
+    S: Store + ?Sized,
+    S::File: Read,
+    P: AsRef<Path>,
+    W: Write,
+{
+    TarPacker::new()
+        .compression(compression)
+        .pack(store, root, writer)
+}
+
+// Decompresses a stream read from the start of the archive.
+fn decoder<R: Read + Send + 'static>(
+    compression: Compression,
+    read: R,
+) -> io::Result<Box<dyn Read + Send>> {
+    Ok(match compression {
+        Compression::None => Box::new(read),
+        Compression::Gzip => Box::new(GzDecoder::new(read)),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Box::new(zstd_::Decoder::new(read)?),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(read)),
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Box::new(bzip2_::read::MultiBzDecoder::new(read)),
+    })
+}
+
+// Returns the target of the link at the given path, if there is one.
+fn tar_link(index: &Index<TarEntry>) -> impl Fn(&Path) -> Option<PathBuf> + '_ {
+    move |path| index.get(path).and_then(|entry| entry.link.clone())
+}
+
+// Looks up a member in the index, following links. Absolute link targets are
+// relative to the root of the archive.
+fn resolve_entry(index: &Index<TarEntry>, path: &Path) -> io::Result<TarEntry> {
+    let path = index::resolve_links(path, Path::new(""), tar_link(index))?;
+    match index.get(path) {
+        Some(entry) => Ok(entry.clone()),
+        None => Err(io::Error::from(ErrorKind::NotFound)),
+    }
+}
+
+fn is_link(entry_type: EntryType) -> bool {
+    entry_type == EntryType::Symlink || entry_type == EntryType::Link
+}
+
+// Error for archives compressed with a disabled feature.
+#[cfg(not(all(feature = "zstd", feature = "xz", feature = "bzip2")))]
+fn unsupported(feature: &str) -> io::Error {
+    let msg = format!(
+        "The archive is compressed with {0}, which requires the `{0}` feature.",
+        feature
+    );
+    io::Error::new(ErrorKind::Unsupported, msg)
+}
+
+// Detects the compression of an archive from its first bytes.
+fn detect_compression<R: Read + ?Sized>(read: &mut R) -> io::Result<Compression> {
+    let mut magic = Vec::with_capacity(10);
+    read.take(10).read_to_end(&mut magic)?;
+    if magic.starts_with(&[0x1f, 0x8b]) {
+        Ok(Compression::Gzip)
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        #[cfg(feature = "zstd")]
+        return Ok(Compression::Zstd);
+        #[cfg(not(feature = "zstd"))]
+        return Err(unsupported("zstd"));
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        #[cfg(feature = "xz")]
+        return Ok(Compression::Xz);
+        #[cfg(not(feature = "xz"))]
+        return Err(unsupported("xz"));
+    } else if is_bzip2(&magic) {
+        #[cfg(feature = "bzip2")]
+        return Ok(Compression::Bzip2);
+        #[cfg(not(feature = "bzip2"))]
+        return Err(unsupported("bzip2"));
+    } else {
+        Ok(Compression::None)
+    }
+}
+
+// A tar member could start with "BZh", so the magic of the first block is
+// checked as well.
+fn is_bzip2(magic: &[u8]) -> bool {
+    match magic {
+        [b'B', b'Z', b'h', level, block @ ..] => {
+            (b'1'..=b'9').contains(level) && block == b"1AY&SY"
+        }
+        _ => false,
+    }
+}
+
+fn read_range<R: Read + ?Sized>(read: &mut R, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+    io::copy(&mut read.take(offset), &mut io::sink())?;
+    let mut data = Vec::with_capacity(size as usize);
+    read.take(size).read_to_end(&mut data)?;
+    if (data.len() as u64) < size {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+// Looks for a member of the archive.
+// Its data is also read if it's no longer than `max_data`.
+//
+// Returns `None` if the path could go through a link, which needs the index to
+// be resolved.
+fn find_read<R: Read>(
+    path: &Path,
+    read: R,
+    max_data: Option<u64>,
+) -> io::Result<Option<(TarEntry, Option<Vec<u8>>)>> {
+    let mut archive = Archive::new(read);
+    let mut links = false;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_type = entry.header().entry_type();
+        links |= is_link(entry_type);
+        if path == entry.path()? && entry_type != EntryType::Directory {
+            if is_link(entry_type) {
+                return Ok(None);
+            }
+            let data = if max_data.is_some_and(|max| entry.size() <= max) {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                Some(data)
+            } else {
+                None
+            };
+            return Ok(Some((TarEntry::new(&entry), data)));
+        }
+    }
+    if links {
+        Ok(None)
+    } else {
+        Err(io::Error::from(ErrorKind::NotFound))
+    }
+}
+
+// Walks the archive once, recording the location of the data of each member.
+fn index_read<R: Read>(entries: tar_::Entries<'_, R>) -> io::Result<Index<TarEntry>> {
+    let mut index = Index::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        match entry.header().entry_type() {
+            EntryType::Directory => index.insert_dir_meta(path, TarEntry::new(&entry)),
+            EntryType::XGlobalHeader | EntryType::XHeader => {}
+            EntryType::Symlink => {
+                let link = entry.link_name()?.map(|link| link.into_owned());
+                index.insert(
+                    path,
+                    TarEntry {
+                        link,
+                        ..TarEntry::new(&entry)
+                    },
+                )
+            }
+            // hard links always come after the member they link to.
+            EntryType::Link => {
+                let target = entry
+                    .link_name()?
+                    .and_then(|link| index.get(&*link).cloned());
+                if let Some(target) = target {
+                    index.insert(path, target);
+                }
+            }
+            _ => index.insert(path, TarEntry::new(&entry)),
+        }
+    }
+    Ok(index)
+}
+
+// Returns `None` if the path could go through a link, like `find_read`.
+fn metadata_read<R: Read>(path: &Path, read: R) -> io::Result<Option<Metadata>> {
+    let mut archive = Archive::new(read);
+    let mut implicit_dir = path.as_os_str().is_empty();
+    let mut links = false;
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let entry_type = entry.header().entry_type();
+        links |= is_link(entry_type);
+        let entry_path = entry.path()?;
+        if path == entry_path {
+            if is_link(entry_type) {
+                return Ok(None);
+            }
+            let kind = if entry_type == EntryType::Directory {
+                EntryKind::Dir
+            } else {
+                EntryKind::File
+            };
+            return Ok(Some(TarEntry::new(&entry).metadata(kind)));
+        }
+        // directories don't need an entry of their own in the archive.
+        if entry_path.starts_with(path) {
+            implicit_dir = true;
+        }
+    }
+    if implicit_dir {
+        Ok(Some(Metadata::dir()))
+    } else if links {
+        Ok(None)
+    } else {
+        Err(io::Error::from(ErrorKind::NotFound))
     }
 }
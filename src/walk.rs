@@ -0,0 +1,202 @@
+//! This module contains a recursive directory walker.
+//!
+//! A [`Walk`] is created with the [`walk`] method of [`StoreExt`], and works
+//! over any store that implements `entries_path`.
+//!
+//! ```
+//! use mini_fs::prelude::*;
+//! use mini_fs::RamFs;
+//!
+//! let mut ram = RamFs::new();
+//! ram.touch("a/b.txt", vec![]);
+//! ram.touch("a/c/d.txt", vec![]);
+//!
+//! let paths: Vec<_> = ram
+//!     .walk("a")
+//!     .sort_by(|a, b| a.path.cmp(&b.path))
+//!     .map(|e| e.unwrap().path)
+//!     .collect();
+//! assert_eq!(3, paths.len());
+//! ```
+//!
+//! [`Walk`]: ./struct.Walk.html
+//! [`walk`]: ../trait.StoreExt.html#method.walk
+//! [`StoreExt`]: ../trait.StoreExt.html
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(any(feature = "tar", feature = "zip"))]
+use crate::store::Metadata;
+use crate::store::{EntryKind, Store};
+
+/// Entry visited by a [`Walk`].
+///
+/// [`Walk`]: ./struct.Walk.html
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WalkEntry {
+    /// Path of the entry, joined to the path the walk started from.
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    /// Depth of the entry. Entries of the starting directory have a depth of 1.
+    pub depth: usize,
+}
+
+impl WalkEntry {
+    // Metadata of the entry, or just its kind if the store doesn't implement
+    // `metadata_path`.
+    #[cfg(any(feature = "tar", feature = "zip"))]
+    pub(crate) fn metadata<S: Store + ?Sized>(&self, store: &S) -> io::Result<Metadata> {
+        match store.metadata_path(&self.path) {
+            Err(ref e) if matches!(e.kind(), io::ErrorKind::Other | io::ErrorKind::Unsupported) => {
+                Ok(Metadata {
+                    len: 0,
+                    kind: self.kind,
+                    modified: None,
+                    mode: None,
+                })
+            }
+            res => res,
+        }
+    }
+}
+
+type SortFn<'a> = Box<dyn FnMut(&WalkEntry, &WalkEntry) -> Ordering + 'a>;
+type FilterFn<'a> = Box<dyn FnMut(&WalkEntry) -> bool + 'a>;
+
+/// Recursive iterator over the entries of a directory.
+///
+/// Entries are visited depth-first, with directories visited before their
+/// contents. Symbolic links to directories are not followed, even if the store
+/// lists them as directories: they are visited as `EntryKind::Symlink`.
+pub struct Walk<'a, S: ?Sized> {
+    store: &'a S,
+    queue: VecDeque<io::Result<WalkEntry>>,
+    // directory to read before visiting the next entry.
+    next_dir: Option<(PathBuf, usize)>,
+    max_depth: usize,
+    breadth_first: bool,
+    sort: Option<SortFn<'a>>,
+    filter: Option<FilterFn<'a>>,
+}
+
+impl<'a, S: Store + ?Sized> Walk<'a, S> {
+    pub(crate) fn new(store: &'a S, path: PathBuf) -> Self {
+        Self {
+            store,
+            queue: VecDeque::new(),
+            next_dir: Some((path, 0)),
+            max_depth: usize::MAX,
+            breadth_first: false,
+            sort: None,
+            filter: None,
+        }
+    }
+
+    /// Don't visit entries deeper than `depth`.
+    ///
+    /// A depth of 1 only visits the entries of the starting directory.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Visit all the entries of a directory before the entries of its
+    /// subdirectories.
+    pub fn breadth_first(mut self) -> Self {
+        self.breadth_first = true;
+        self
+    }
+
+    /// Sort the entries of each directory.
+    ///
+    /// Entries are visited in the order returned by the store otherwise.
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: FnMut(&WalkEntry, &WalkEntry) -> Ordering + 'a,
+    {
+        self.sort = Some(Box::new(compare));
+        self
+    }
+
+    /// Skip the entries for which the predicate returns false.
+    ///
+    /// Skipped directories are not descended into.
+    pub fn filter_entry<F>(mut self, predicate: F) -> Self
+    where
+        F: FnMut(&WalkEntry) -> bool + 'a,
+    {
+        self.filter = Some(Box::new(predicate));
+        self
+    }
+
+    // Queues the entries of a directory.
+    fn read_dir(&mut self, dir: &Path, depth: usize) -> io::Result<()> {
+        let mut entries: Vec<_> = self
+            .store
+            .entries_path(dir)?
+            .map(|entry| {
+                let entry = entry?;
+                let path = dir.join(entry.file_name());
+                let mut kind = entry.kind;
+                // stores that follow links list them with the kind of their
+                // target, and a link to a parent directory would never end.
+                if kind == EntryKind::Dir && self.store.read_link_path(&path).is_ok() {
+                    kind = EntryKind::Symlink;
+                }
+                Ok(WalkEntry {
+                    path,
+                    kind,
+                    depth: depth + 1,
+                })
+            })
+            .collect();
+        if let Some(ref mut sort) = self.sort {
+            // errors go first.
+            entries.sort_by(|a, b| match (a, b) {
+                (Ok(a), Ok(b)) => sort(a, b),
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => Ordering::Equal,
+            });
+        }
+        if self.breadth_first {
+            self.queue.extend(entries);
+        } else {
+            for entry in entries.into_iter().rev() {
+                self.queue.push_front(entry);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: Store + ?Sized> Iterator for Walk<'_, S> {
+    type Item = io::Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((dir, depth)) = self.next_dir.take() {
+            if depth < self.max_depth {
+                if let Err(err) = self.read_dir(&dir, depth) {
+                    return Some(Err(err));
+                }
+            }
+        }
+        loop {
+            let entry = match self.queue.pop_front()? {
+                Ok(entry) => entry,
+                Err(err) => return Some(Err(err)),
+            };
+            if let Some(ref mut filter) = self.filter {
+                if !filter(&entry) {
+                    continue;
+                }
+            }
+            if entry.kind == EntryKind::Dir {
+                self.next_dir = Some((entry.path.clone(), entry.depth));
+            }
+            return Some(Ok(entry));
+        }
+    }
+}
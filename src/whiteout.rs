@@ -0,0 +1,195 @@
+//! This module contains the whiteouts used by overlays.
+//!
+//! A whiteout is a marker file that hides a file or directory of the lower
+//! layers of an overlay (the tuple and `Vec` stores), following the naming
+//! convention of overlayfs and AUFS:
+//!
+//! - An empty file named `.wh.<name>` hides `<name>` from the lower layers.
+//! - A file named `.wh..wh..opq` makes its directory opaque, which hides all of
+//!   its contents from the lower layers.
+//!
+//! Whiteouts only hide files from the layers below the one that contains them,
+//! and are never listed. They can be created in any store, or with the
+//! [`whiteout`] and [`opaque`] methods of [`RamFs`].
+//!
+//! ```
+//! use mini_fs::prelude::*;
+//! use mini_fs::RamFs;
+//!
+//! let mut base = RamFs::new();
+//! base.touch("a.txt", vec![]);
+//! base.touch("b.txt", vec![]);
+//!
+//! let mut patch = RamFs::new();
+//! patch.whiteout("a.txt");
+//!
+//! let fs = vec![patch, base];
+//! assert!(fs.open("a.txt").is_err());
+//! assert_eq!(1, fs.entries("").unwrap().count());
+//! ```
+//!
+//! [`whiteout`]: ../struct.RamFs.html#method.whiteout
+//! [`opaque`]: ../struct.RamFs.html#method.opaque
+//! [`RamFs`]: ../struct.RamFs.html
+use std::collections::BTreeSet;
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::store::{Entries, Entry, Store};
+
+/// Prefix of the name of a whiteout.
+pub const PREFIX: &str = ".wh.";
+
+/// Name of the marker of an opaque directory.
+pub const OPAQUE: &str = ".wh..wh..opq";
+
+/// Name of the whiteout that hides `name`.
+pub fn whiteout_name(name: &OsStr) -> OsString {
+    let mut whiteout = OsString::from(PREFIX);
+    whiteout.push(name);
+    whiteout
+}
+
+fn exists<S: Store + ?Sized>(store: &S, path: &Path) -> bool {
+    store.metadata_path(path).is_ok()
+}
+
+/// Returns true if the store hides the path from the lower layers, either with
+/// a whiteout of the path (or one of its parents) or an opaque parent.
+pub(crate) fn hides<S: Store + ?Sized>(store: &S, path: &Path) -> bool {
+    let mut dir = PathBuf::new();
+    let mut nested = false;
+    for comp in path.components() {
+        if let Component::Normal(name) = comp {
+            // there are no markers in the directories the store doesn't have.
+            if nested && !store.metadata_path(&dir).is_ok_and(|meta| meta.is_dir()) {
+                return false;
+            }
+            if exists(store, &dir.join(OPAQUE)) || exists(store, &dir.join(whiteout_name(name))) {
+                return true;
+            }
+            nested = true;
+        }
+        dir.push(comp);
+    }
+    false
+}
+
+/// Looks up a path in the layers of an overlay, from the top.
+///
+/// Returns the result of the first layer that contains the path (anything but
+/// a `NotFound` error), or `NotFound` if a layer above it hides the path.
+/// Layers are only checked for whiteouts once a layer below them contains the
+/// path, so paths that aren't in any layer don't look for them at all.
+pub(crate) struct Lookup<'a, T> {
+    path: &'a Path,
+    result: Option<io::Result<T>>,
+    // layers above the current one that don't contain the path.
+    above: Vec<Box<dyn Fn() -> bool + 'a>>,
+}
+
+impl<'a, T> Lookup<'a, T> {
+    pub(crate) fn new(path: &'a Path) -> Self {
+        Self {
+            path,
+            result: None,
+            above: Vec::new(),
+        }
+    }
+
+    /// Runs the operation on the next layer, unless the path has been found.
+    pub(crate) fn layer<S, F>(mut self, store: &'a S, op: F) -> Self
+    where
+        S: Store + ?Sized,
+        F: FnOnce(&S, &Path) -> io::Result<T>,
+    {
+        if self.result.is_some() {
+            return self;
+        }
+        let path = self.path;
+        match op(store, path) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                self.above.push(Box::new(move || hides(store, path)));
+            }
+            res if self.above.iter().any(|hides| hides()) => {
+                drop(res);
+                self.result = Some(Err(io::ErrorKind::NotFound.into()));
+            }
+            res => self.result = Some(res),
+        }
+        self
+    }
+
+    pub(crate) fn finish(self) -> io::Result<T> {
+        self.result
+            .unwrap_or_else(|| Err(io::ErrorKind::NotFound.into()))
+    }
+}
+
+/// Lists a directory of one layer of an overlay.
+///
+/// Also returns true if the entries of the lower layers are hidden.
+pub(crate) fn layer_entries<'a, S: Store + ?Sized>(
+    store: &'a S,
+    path: &Path,
+) -> io::Result<(Entries<'a>, bool)> {
+    // the marker of an opaque directory is one of its entries.
+    let entries: Vec<_> = store.entries_path(path)?.collect();
+    let opaque = entries.iter().any(|entry| match *entry {
+        Ok(ref entry) => entry.file_name() == OPAQUE,
+        Err(_) => false,
+    });
+    let opaque = opaque || hides(store, path);
+    Ok((Entries::new(entries), opaque))
+}
+
+/// Entries of the layers of an overlay, skipping duplicates and the entries
+/// hidden by whiteouts.
+pub(crate) struct OverlayEntries<'a> {
+    layers: Vec<Entries<'a>>,
+    // names that have been returned, or hidden by the layers above.
+    seen: BTreeSet<OsString>,
+    // names hidden by the whiteouts of the current layer.
+    hidden: Vec<OsString>,
+}
+
+impl<'a> OverlayEntries<'a> {
+    pub(crate) fn new(mut layers: Vec<Entries<'a>>) -> Self {
+        layers.reverse();
+        Self {
+            layers,
+            seen: BTreeSet::new(),
+            hidden: Vec::new(),
+        }
+    }
+}
+
+impl Iterator for OverlayEntries<'_> {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.layers.last_mut()?.next() {
+                Some(Ok(entry)) => entry,
+                Some(Err(err)) => return Some(Err(err)),
+                None => {
+                    // whiteouts only apply to the lower layers.
+                    self.seen.extend(self.hidden.drain(..));
+                    self.layers.pop();
+                    continue;
+                }
+            };
+            let name = entry.file_name();
+            if let Some(hidden) = name.to_str().and_then(|name| name.strip_prefix(PREFIX)) {
+                if name != OPAQUE {
+                    self.hidden.push(hidden.into());
+                }
+                continue;
+            }
+            if self.seen.insert(name.to_os_string()) {
+                return Some(Ok(entry));
+            }
+        }
+    }
+}
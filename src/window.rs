@@ -0,0 +1,83 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+
+use crate::sync::lock;
+
+/// Reader that can be shared between a store and the files opened from it.
+pub(crate) trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+pub(crate) type SharedReader = Arc<Mutex<dyn ReadSeek + Send>>;
+
+/// Bounded view over a region of a shared reader.
+///
+/// The position of the underlying reader is restored before every read, so
+/// several windows (and the store that owns the reader) can be used at the
+/// same time.
+pub(crate) struct Window<R: ?Sized = dyn ReadSeek + Send> {
+    reader: Arc<Mutex<R>>,
+    start: u64,
+    len: u64,
+    pos: u64,
+}
+
+impl<R: ?Sized> Window<R> {
+    pub(crate) fn new(reader: Arc<Mutex<R>>, start: u64, len: u64) -> Self {
+        Self {
+            reader,
+            start,
+            len,
+            pos: 0,
+        }
+    }
+}
+
+impl<R: ?Sized> Clone for Window<R> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: Arc::clone(&self.reader),
+            start: self.start,
+            len: self.len,
+            pos: self.pos,
+        }
+    }
+}
+
+impl<R: Read + Seek + ?Sized> Read for Window<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        if remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let max = buf.len().min(remaining as usize);
+        let mut reader = lock(&self.reader);
+        reader.seek(SeekFrom::Start(self.start + self.pos))?;
+        let read = reader.read(&mut buf[..max])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: ?Sized> Seek for Window<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_position(pos, self.pos, self.len)?;
+        Ok(self.pos)
+    }
+}
+
+/// Resolves a seek within a region of `len` bytes. Seeking past the end is
+/// allowed, like in a `Cursor`.
+pub(crate) fn seek_position(pos: SeekFrom, current: u64, len: u64) -> io::Result<u64> {
+    let pos = match pos {
+        SeekFrom::Start(pos) => Some(pos),
+        SeekFrom::End(off) => len.checked_add_signed(off),
+        SeekFrom::Current(off) => current.checked_add_signed(off),
+    };
+    pos.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
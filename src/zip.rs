@@ -1,13 +1,27 @@
-use std::cell::RefCell;
+use std::convert::TryFrom;
 use std::fs;
-use std::io::{self, Cursor, ErrorKind, Read, Seek, SeekFrom};
+use std::io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use zip_::ZipArchive;
+use flate2::read::DeflateDecoder;
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+use zip_::read::{ZipFile, ZipFileEntry};
+use zip_::result::ZipError;
+use zip_::write::{SimpleFileOptions, ZipWriter};
+use zip_::{CompressionMethod as ZipMethod, DateTime, ZipArchive};
 
-use crate::index::Index;
+use crate::index::{normalize_path, Index};
+#[cfg(feature = "mmap")]
+use crate::mmap::MmapFile;
 use crate::store::Store;
-use crate::{Entries, Entry};
+use crate::stream::{OpenFn, Stream};
+use crate::sync::lock;
+use crate::walk::Walk;
+use crate::window::{SharedReader, Window};
+use crate::{Entries, Entry, EntryKind, Metadata};
 
 /// Zip archive store.
 ///
@@ -15,99 +29,624 @@ use crate::{Entries, Entry};
 ///
 /// When used with a `std::fs::File`, the file will remain open for the lifetime
 /// of the Zip.
+///
+/// The contents of the archive are indexed the first time they are listed,
+/// unless the [`index`] method has been called before.
+///
+/// The central directory of the archive is parsed once, the first time the
+/// archive is read, and files are opened from the offsets it records.
+///
+/// Files are decompressed into memory when they are opened. Use
+/// [`stream_files`] to read them from the archive as they are read instead.
+///
+/// Zip64 archives are supported, as well as files stored without compression
+/// or compressed with deflate and deflate64. Files compressed with bzip2,
+/// zstd, lzma or xz need the `bzip2`, `zstd`, `lzma` or `xz` features, and fail
+/// to open with an `Unsupported` error otherwise.
+///
+/// Encrypted files (ZipCrypto, or AES with the `aes` feature) can be opened
+/// once a password has been set with [`with_password`] or
+/// [`with_password_fn`]. Opening them fails with a `PermissionDenied` error
+/// when there is no password, or it's wrong.
+///
+/// [`index`]: #method.index
+/// [`stream_files`]: #method.stream_files
+/// [`with_password`]: #method.with_password
+/// [`with_password_fn`]: #method.with_password_fn
 pub struct ZipFs<T: Read + Seek> {
-    inner: RefCell<T>,
-    index: Option<Index<()>>,
+    inner: Arc<Mutex<T>>,
+    // Parsed on the first read. Files are read from clones of it, which share
+    // the parsed metadata.
+    archive: OnceLock<ZipArchive<Window<T>>>,
+    index: OnceLock<Index<()>>,
+    password: Option<PasswordFn>,
+    max_buffered: Option<u64>,
+    // The inner reader, shared with the files that read from it. Only set by
+    // `stream_files`, which requires the reader to be `Send + 'static`.
+    stream: Option<SharedReader>,
+    #[cfg(feature = "mmap")]
+    mmap: Option<Arc<Mmap>>,
 }
 
+type PasswordFn = Box<dyn Fn(&Path) -> Option<Vec<u8>> + Send + Sync>;
+
 /// Entry in the Zip archive.
 pub struct ZipFsFile {
-    inner: Cursor<Box<[u8]>>,
+    inner: ZipFsFileInner,
+}
+
+enum ZipFsFileInner {
+    Buffered(Cursor<Box<[u8]>>),
+    Window(BufReader<Window>),
+    Stream(Stream),
+    #[cfg(feature = "mmap")]
+    Mmap(MmapFile),
+}
+
+impl ZipFsFile {
+    /// Returns the (uncompressed) contents of the file.
+    ///
+    /// Returns `None` if the file is read from the archive as it's read, which
+    /// can only happen when [`ZipFs::stream_files`] is used.
+    ///
+    /// [`ZipFs::stream_files`]: ./struct.ZipFs.html#method.stream_files
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.inner {
+            ZipFsFileInner::Buffered(ref file) => Some(file.get_ref()),
+            ZipFsFileInner::Window(_) | ZipFsFileInner::Stream(_) => None,
+            #[cfg(feature = "mmap")]
+            ZipFsFileInner::Mmap(ref file) => Some(file.as_bytes()),
+        }
+    }
 }
 
 impl Read for ZipFsFile {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
+        match self.inner {
+            ZipFsFileInner::Buffered(ref mut file) => file.read(buf),
+            ZipFsFileInner::Window(ref mut file) => file.read(buf),
+            ZipFsFileInner::Stream(ref mut file) => file.read(buf),
+            #[cfg(feature = "mmap")]
+            ZipFsFileInner::Mmap(ref mut file) => file.read(buf),
+        }
     }
 }
 
 impl Seek for ZipFsFile {
     #[inline]
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
-        self.inner.seek(pos)
+        match self.inner {
+            ZipFsFileInner::Buffered(ref mut file) => file.seek(pos),
+            ZipFsFileInner::Window(ref mut file) => file.seek(pos),
+            ZipFsFileInner::Stream(ref mut file) => file.seek(pos),
+            #[cfg(feature = "mmap")]
+            ZipFsFileInner::Mmap(ref mut file) => file.seek(pos),
+        }
     }
 }
 
 impl ZipFs<fs::File> {
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = fs::OpenOptions::new()
-            .read(true)
-            .write(false)
-            .create(false)
-            .open(path)?;
-        Ok(Self::new(file))
+        Ok(Self::new(open_file(path.as_ref())?))
+    }
+
+    /// Open a file from the native filesystem and memory-map it.
+    ///
+    /// Files stored without compression are returned as slices of the mapped
+    /// archive instead of being copied. See the [`mmap`] module for the safety
+    /// caveats.
+    ///
+    /// [`mmap`]: ../mmap/index.html
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = open_file(path.as_ref())?;
+        // Safety: see the mmap module docs.
+        let map = unsafe { Mmap::map(&file)? };
+        let mut zip = Self::new(file);
+        zip.mmap = Some(Arc::new(map));
+        Ok(zip)
     }
 }
 
 impl<T: Read + Seek> ZipFs<T> {
     pub fn new(inner: T) -> Self {
         Self {
-            inner: RefCell::new(inner),
-            index: None,
+            inner: Arc::new(Mutex::new(inner)),
+            archive: OnceLock::new(),
+            index: OnceLock::new(),
+            password: None,
+            max_buffered: None,
+            stream: None,
+            #[cfg(feature = "mmap")]
+            mmap: None,
         }
     }
 
+    /// Decrypt the encrypted files of the archive with the given password.
+    pub fn with_password<P: Into<Vec<u8>>>(self, password: P) -> Self {
+        let password = password.into();
+        self.with_password_fn(move |_| Some(password.clone()))
+    }
+
+    /// Decrypt the encrypted files of the archive with the password returned
+    /// by the closure for their path, if any.
+    pub fn with_password_fn<F>(mut self, password: F) -> Self
+    where
+        F: Fn(&Path) -> Option<Vec<u8>> + Send + Sync + 'static,
+    {
+        self.password = Some(Box::new(password));
+        self
+    }
+
+    /// Decompress files of up to `max_len` bytes into memory when they are
+    /// opened, instead of reading them from the archive as they are read.
+    ///
+    /// Only has an effect on a store that [streams its files].
+    ///
+    /// [streams its files]: #method.stream_files
+    pub fn buffer_small_files(mut self, max_len: u64) -> Self {
+        self.max_buffered = Some(max_len);
+        self
+    }
+
     /// Index the contents of the archive.
     ///
-    /// Having an index allows you to list the contents of the archive using the
-    /// entries_path and entries methods.
-    pub fn index(mut self) -> io::Result<Self> {
+    /// The index is used to list the contents of the archive using the
+    /// entries_path and entries methods. It is built on the first listing if
+    /// this method isn't called.
+    pub fn index(self) -> io::Result<Self> {
+        self.get_index()?;
+        Ok(self)
+    }
+
+    // Returns the archive, parsing it first if needed.
+    fn archive(&self) -> io::Result<&ZipArchive<Window<T>>> {
+        if let Some(archive) = self.archive.get() {
+            return Ok(archive);
+        }
+        let len = lock(&self.inner).seek(SeekFrom::End(0))?;
+        let window = Window::new(Arc::clone(&self.inner), 0, len);
+        let archive = ZipArchive::new(window)?;
+        Ok(self.archive.get_or_init(|| archive))
+    }
+
+    fn get_index(&self) -> io::Result<&Index<()>> {
+        if let Some(index) = self.index.get() {
+            return Ok(index);
+        }
+        let archive = self.archive()?;
         let mut index = Index::new();
-        let mut file = self.inner.borrow_mut();
-        file.seek(SeekFrom::Start(0))?;
-        let mut archive = ZipArchive::new(&mut *file)?;
         for i in 0..archive.len() {
-            let file = archive.by_index(i)?;
-            let path = file.sanitized_name();
+            let file = archive.by_index_data(i)?;
+            let path = file.mangled_name()?;
+            if file.is_dir() {
+                index.insert_dir(path);
+            } else {
+                index.insert(path, ());
+            }
+        }
+        Ok(self.index.get_or_init(|| index))
+    }
 
-            index.insert(path, ());
+    // Opens a file that isn't encrypted without decompressing it up front, if
+    // it's stored or compressed with deflate.
+    fn open_stream<R: Read + Seek>(&self, file: &ZipFile<'_, R>) -> Option<ZipFsFile> {
+        if self.max_buffered.is_some_and(|max| file.size() <= max) {
+            return None;
         }
-        self.index = Some(index);
-        drop(file);
-        Ok(self)
+        let reader = Arc::clone(self.stream.as_ref()?);
+        let start = file.data_start()?;
+        let inner = match file.compression() {
+            ZipMethod::Stored => {
+                let window = Window::new(reader, start, file.size());
+                ZipFsFileInner::Window(BufReader::new(window))
+            }
+            ZipMethod::Deflated => {
+                let len = file.compressed_size();
+                let open: OpenFn = Box::new(move |_| {
+                    let window = Window::new(Arc::clone(&reader), start, len);
+                    Ok((Box::new(DeflateDecoder::new(window)), 0))
+                });
+                ZipFsFileInner::Stream(Stream::new(open, 0, file.size()).crc32(file.crc32()))
+            }
+            _ => return None,
+        };
+        Some(ZipFsFile { inner })
+    }
+}
+
+impl<T: Read + Seek + Send + 'static> ZipFs<T> {
+    /// Read files from the archive as they are read, instead of decompressing
+    /// them into memory when they are opened.
+    ///
+    /// Files stored without compression are read directly from the underlying
+    /// reader, and files compressed with deflate are decompressed as they are
+    /// read. Seeking backwards in a compressed file restarts its
+    /// decompression. Use [`buffer_small_files`] to keep decompressing small
+    /// files into memory. Files compressed with other methods, and encrypted
+    /// files, are always decompressed into memory when they are opened.
+    ///
+    /// The files keep a handle to the reader, which is why it has to be
+    /// `Send + 'static`.
+    ///
+    /// [`buffer_small_files`]: #method.buffer_small_files
+    pub fn stream_files(mut self) -> Self {
+        let reader: SharedReader = self.inner.clone();
+        self.stream = Some(reader);
+        self
     }
 }
 
 impl<T: Read + Seek> Store for ZipFs<T> {
     type File = ZipFsFile;
     fn open_path(&self, path: &Path) -> io::Result<Self::File> {
-        let mut file = self.inner.borrow_mut();
-        file.seek(SeekFrom::Start(0))?;
-
-        let mut archive = ZipArchive::new(&mut *file)?;
-        let name = path.to_str().ok_or(io::Error::new(
-            ErrorKind::Other,
-            "Utf8 path conversion error.",
-        ));
-        let mut file = archive.by_name(name?)?;
+        let archive = self.archive()?;
+        let index = match archive.index_for_name(zip_name(path)?) {
+            Some(index) => index,
+            None => return Err(io::Error::from(io::ErrorKind::NotFound)),
+        };
+        let encrypted = {
+            let data = archive.by_index_data(index)?;
+            check_method(data.compression())?;
+            data.encrypted()
+        };
+        // read from a copy of the archive, so files can be opened and read at
+        // the same time.
+        let mut archive = archive.clone();
+        let mut file = if encrypted {
+            let password = self.password.as_ref().and_then(|password| password(path));
+            let password = password.ok_or_else(|| denied("The file is encrypted."))?;
+            match archive.by_index_decrypt(index, &password) {
+                Ok(file) => file,
+                Err(ZipError::InvalidPassword) => return Err(denied("Invalid password.")),
+                Err(err) => return Err(err.into()),
+            }
+        } else {
+            let raw = archive.by_index_raw(index)?;
+            #[cfg(feature = "mmap")]
+            {
+                let stored = raw.compression() == ZipMethod::Stored;
+                if let (true, Some(map), Some(start)) = (stored, &self.mmap, raw.data_start()) {
+                    let start = start as usize;
+                    let len = raw.size() as usize;
+                    if start.checked_add(len).is_some_and(|end| end <= map.len()) {
+                        let file = MmapFile::slice(Arc::clone(map), start, len);
+                        return Ok(ZipFsFile {
+                            inner: ZipFsFileInner::Mmap(file),
+                        });
+                    }
+                }
+            }
+            if let Some(file) = self.open_stream(&raw) {
+                return Ok(file);
+            }
+            drop(raw);
+            archive.by_index(index)?
+        };
 
         let mut v = Vec::new();
-        file.read_to_end(&mut v)?;
+        match file.read_to_end(&mut v) {
+            // ZipCrypto only detects most wrong passwords up front, the others
+            // fail the checksum.
+            Err(ref err) if encrypted && err.kind() == io::ErrorKind::InvalidData => {
+                return Err(denied("Invalid password."))
+            }
+            res => res?,
+        };
         Ok(ZipFsFile {
-            inner: Cursor::new(v.into()),
+            inner: ZipFsFileInner::Buffered(Cursor::new(v.into())),
         })
     }
 
-    fn entries_path(&self, path: &Path) -> io::Result<Entries> {
-        if let Some(ref idx) = self.index {
-            Ok(Entries::new(idx.entries(path).map(|ent| {
-                let name = ent.name.to_os_string();
-                let kind = ent.kind;
-                Ok(Entry { name, kind })
-            })))
+    fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+        let idx = self.get_index()?;
+        Ok(Entries::new(idx.entries(path).map(|ent| {
+            let name = ent.name.to_os_string();
+            let kind = ent.kind;
+            Ok(Entry { name, kind })
+        })))
+    }
+
+    fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+        let name = zip_name(path)?.trim_end_matches('/');
+        if name.is_empty() {
+            return Ok(Metadata::dir());
+        }
+
+        let archive = self.archive()?;
+        // directories are stored with a trailing slash, if they are stored at
+        // all.
+        let dir = format!("{}/", name);
+        let index = archive
+            .index_for_name(name)
+            .or_else(|| archive.index_for_name(&dir));
+        if let Some(index) = index {
+            return Ok(zip_metadata(&archive.by_index_data(index)?));
+        }
+        if archive
+            .file_names()
+            .any(|n| n.is_ok_and(|n| n.starts_with(&dir)))
+        {
+            Ok(Metadata::dir())
         } else {
-            panic!("You have to call the `Zip::index` method on this zip archive before you can list its entries.")
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+}
+
+/// Compression method of the files written by a [`ZipPacker`].
+///
+/// [`ZipPacker`]: ./struct.ZipPacker.html
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CompressionMethod {
+    /// Files are stored without compression.
+    Stored,
+    /// Deflate compression, supported by every zip reader.
+    Deflated,
+    /// Bzip2 compression.
+    #[cfg(feature = "bzip2")]
+    Bzip2,
+    /// Zstd compression.
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// Xz compression.
+    #[cfg(feature = "xz")]
+    Xz,
+}
+
+impl CompressionMethod {
+    fn to_zip(self) -> ZipMethod {
+        match self {
+            CompressionMethod::Stored => ZipMethod::Stored,
+            CompressionMethod::Deflated => ZipMethod::Deflated,
+            #[cfg(feature = "bzip2")]
+            CompressionMethod::Bzip2 => ZipMethod::Bzip2,
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => ZipMethod::Zstd,
+            #[cfg(feature = "xz")]
+            CompressionMethod::Xz => ZipMethod::Xz,
         }
     }
 }
+
+type MethodFn<'a> = Box<dyn Fn(&Path) -> CompressionMethod + 'a>;
+
+/// Writes the files of a store into a zip archive.
+///
+/// Entries are written in the order of their paths, with their modification
+/// times and permissions when the store keeps track of them, so the same files
+/// always produce the same archive.
+///
+/// ```
+/// use mini_fs::zip::{CompressionMethod, ZipPacker};
+/// use mini_fs::RamFs;
+/// use std::io::Cursor;
+///
+/// let mut ram = RamFs::new();
+/// ram.touch("gfx/a.png", vec![0; 64]);
+/// ram.touch("sfx/b.ogg", vec![0; 64]);
+///
+/// // images are already compressed.
+/// let packer = ZipPacker::new().compression_method_by(|path| {
+///     match path.extension().and_then(|ext| ext.to_str()) {
+///         Some("png") => CompressionMethod::Stored,
+///         _ => CompressionMethod::Deflated,
+///     }
+/// });
+/// let zip = packer.pack(&ram, "", Cursor::new(Vec::new()))?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct ZipPacker<'a> {
+    method: MethodFn<'a>,
+    modified: Option<SystemTime>,
+}
+
+impl Default for ZipPacker<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ZipPacker<'a> {
+    /// Creates a packer that compresses every file with deflate.
+    pub fn new() -> Self {
+        Self {
+            method: Box::new(|_| CompressionMethod::Deflated),
+            modified: None,
+        }
+    }
+
+    /// Compress every file with the given method.
+    pub fn compression_method(mut self, method: CompressionMethod) -> Self {
+        self.method = Box::new(move |_| method);
+        self
+    }
+
+    /// Choose the compression method of each file from its path in the
+    /// archive.
+    pub fn compression_method_by<F>(mut self, method: F) -> Self
+    where
+        F: Fn(&Path) -> CompressionMethod + 'a,
+    {
+        self.method = Box::new(method);
+        self
+    }
+
+    /// Use the same modification time for every entry, instead of the times
+    /// from the store.
+    ///
+    /// Entries without a modification time use the earliest zip timestamp
+    /// (1980-01-01) otherwise.
+    pub fn modified(mut self, time: SystemTime) -> Self {
+        self.modified = Some(time);
+        self
+    }
+
+    /// Writes the contents of a directory of the store into a zip archive, and
+    /// returns the writer.
+    ///
+    /// Paths in the archive are relative to `root`. Symbolic links to files
+    /// are stored as copies of the files, and links to directories are
+    /// skipped. Stores that don't implement `metadata_path` can be packed too,
+    /// without modification times or permissions.
+    pub fn pack<S, P, W>(&self, store: &S, root: P, writer: W) -> io::Result<W>
+    where
+        S: Store + ?Sized,
+        S::File: Read,
+        P: AsRef<Path>,
+        W: Write + Seek,
+    {
+        let root = normalize_path(root.as_ref()).into_owned();
+        let mut zip = ZipWriter::new(writer);
+        let walk = Walk::new(store, root.clone()).sort_by(|a, b| a.path.cmp(&b.path));
+        for entry in walk {
+            let entry = entry?;
+            let path = entry.path.strip_prefix(&root).unwrap_or(&entry.path);
+            let name = zip_name(path)?.replace('\\', "/");
+            let meta = entry.metadata(store)?;
+            let modified = self.modified.or(meta.modified);
+            let mut options = SimpleFileOptions::default()
+                .last_modified_time(modified.and_then(zip_time).unwrap_or_default());
+            if let Some(mode) = meta.mode {
+                options = options.unix_permissions(mode);
+            }
+            match meta.kind {
+                EntryKind::Dir if entry.kind == EntryKind::Dir => {
+                    zip.add_directory(name, options)?;
+                }
+                EntryKind::File => {
+                    let options = options
+                        .compression_method((self.method)(path).to_zip())
+                        .large_file(meta.len >= u64::from(u32::MAX));
+                    zip.start_file(name, options)?;
+                    io::copy(&mut store.open_path(&entry.path)?, &mut zip)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(zip.finish()?)
+    }
+}
+
+/// Writes the contents of a directory of the store into a zip archive, with
+/// the default options of a [`ZipPacker`].
+///
+/// [`ZipPacker`]: ./struct.ZipPacker.html
+pub fn pack_zip<S, P, W>(store: &S, root: P, writer: W) -> io::Result<W>
+where
+    S: Store + ?Sized,
+    S::File: Read,
+    P: AsRef<Path>,
+    W: Write + Seek,
+{
+    ZipPacker::new().pack(store, root, writer)
+}
+
+fn denied(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::PermissionDenied, msg)
+}
+
+fn open_file(path: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new()
+        .read(true)
+        .write(false)
+        .create(false)
+        .open(path)
+}
+
+fn zip_name(path: &Path) -> io::Result<&str> {
+    path.to_str()
+        .ok_or_else(|| io::Error::other("Utf8 path conversion error."))
+}
+
+// Fails if the compression method isn't supported, naming the method or the
+// feature that supports it.
+#[allow(deprecated)]
+fn check_method(method: ZipMethod) -> io::Result<()> {
+    let feature = match method {
+        ZipMethod::Unsupported(12) => "bzip2",
+        ZipMethod::Unsupported(14) => "lzma",
+        ZipMethod::Unsupported(93) => "zstd",
+        ZipMethod::Unsupported(95) => "xz",
+        ZipMethod::Unsupported(99) => "aes",
+        ZipMethod::Unsupported(method) => {
+            let msg = format!("Unsupported compression method ({}).", method);
+            return Err(io::Error::new(io::ErrorKind::Unsupported, msg));
+        }
+        _ => return Ok(()),
+    };
+    let msg = format!(
+        "The file is compressed with {0}, which requires the `{0}` feature.",
+        feature
+    );
+    Err(io::Error::new(io::ErrorKind::Unsupported, msg))
+}
+
+fn zip_metadata(file: &ZipFileEntry<'_>) -> Metadata {
+    let kind = if file.is_dir() {
+        EntryKind::Dir
+    } else {
+        EntryKind::File
+    };
+    Metadata {
+        len: if file.is_dir() { 0 } else { file.size() },
+        kind,
+        modified: file.last_modified().and_then(system_time),
+        mode: file.unix_mode(),
+    }
+}
+
+// Converts an MS-DOS timestamp from the archive into a SystemTime.
+//
+// Zip timestamps don't store a timezone, so they are interpreted as UTC.
+fn system_time(time: DateTime) -> Option<SystemTime> {
+    // days since the epoch, from http://howardhinnant.github.io/date_algorithms.html
+    let (y, m, d) = (
+        i64::from(time.year()),
+        i64::from(time.month()),
+        i64::from(time.day()),
+    );
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((m + 9) % 12) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = days * 86_400
+        + i64::from(time.hour()) * 3_600
+        + i64::from(time.minute()) * 60
+        + i64::from(time.second());
+    u64::try_from(secs)
+        .ok()
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+// Converts a SystemTime into an MS-DOS timestamp, in UTC.
+//
+// Returns None if the time is outside of the range of zip timestamps.
+fn zip_time(time: SystemTime) -> Option<DateTime> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let (days, secs) = (i64::try_from(secs / 86_400).ok()?, secs % 86_400);
+    // civil date from days, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+
+    DateTime::from_date_and_time(
+        u16::try_from(y).ok()?,
+        m as u8,
+        d as u8,
+        (secs / 3_600) as u8,
+        (secs / 60 % 60) as u8,
+        (secs % 60) as u8,
+    )
+    .ok()
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "tokio")]
+use futures_core::Stream;
+use mini_fs::{AsyncEntries, AsyncStoreExt, EntryKind, LocalFs, RamFs};
+use std::future::{poll_fn, Future};
+use std::io::{Read, Result};
+use std::pin::Pin;
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio_::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+async fn collect(mut entries: AsyncEntries) -> Result<Vec<mini_fs::Entry>> {
+    let mut vec = Vec::new();
+    while let Some(entry) = poll_fn(|cx| Pin::new(&mut entries).poll_next(cx)).await {
+        vec.push(entry?);
+    }
+    vec.sort_by_key(|e| e.name.clone());
+    Ok(vec)
+}
+
+#[test]
+fn local_fs_async() {
+    block_on(async {
+        let local = LocalFs::new("./tests/local");
+
+        let entries = collect(local.entries("./").await.unwrap()).await.unwrap();
+        assert_eq!(3, entries.len());
+        assert_eq!("baz", entries[1].name);
+        assert_eq!(EntryKind::Dir, entries[1].kind);
+
+        assert!(local.metadata("baz").await.unwrap().is_dir());
+        assert!(local.metadata("nope").await.is_err());
+
+        let mut file = local.open("foo").await.unwrap().into_std().await;
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!("", content);
+        assert!(local.open("nope").await.is_err());
+    })
+}
+
+#[test]
+fn ram_fs_async() {
+    block_on(async {
+        let mut ram = RamFs::new();
+        ram.touch("a/b.txt", b"hello".to_vec());
+
+        let mut content = String::new();
+        let mut file = ram.open("a/b.txt").await.unwrap();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!("hello", content);
+
+        let entries = collect(ram.entries("a").await.unwrap()).await.unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!(5, ram.metadata("a/b.txt").await.unwrap().len);
+    })
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn blocking_async() {
+    use mini_fs::{Blocking, ZipFs};
+
+    block_on(async {
+        let zip = Blocking::new(ZipFs::open("tests/archive2.zip").unwrap());
+
+        let mut content = String::new();
+        let mut file = zip.open("nested/hello.txt").await.unwrap();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!("hello\n", content);
+
+        let entries = collect(zip.entries("nested").await.unwrap()).await.unwrap();
+        assert_eq!(2, entries.len());
+        assert!(zip.metadata("nested").await.unwrap().is_dir());
+        assert!(zip.open("nope").await.is_err());
+    })
+}
@@ -50,10 +50,10 @@ fn caseless() {
     let mut txt = String::new();
     let mut file = caseless.open("/A.tXt").unwrap();
     file.read_to_string(&mut txt).unwrap();
-    assert_eq!(["low a", "high a"].iter().any(|s| s == &txt), true);
+    assert!(["low a", "high a"].iter().any(|s| s == &txt));
 
     let mut txt = String::new();
     let mut file = caseless.open("/b/B.tXt").unwrap();
     file.read_to_string(&mut txt).unwrap();
-    assert_eq!(["low b", "high b"].iter().any(|s| s == &txt), true);
+    assert!(["low b", "high b"].iter().any(|s| s == &txt));
 }
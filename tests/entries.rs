@@ -57,7 +57,7 @@ fn overriden_entries_0() {
 
     let files = MiniFs::new().mount("/f0", f0).mount("/f0", f1);
 
-    let mut entries = files
+    let entries = files
         .entries("/f0")
         .unwrap()
         .collect::<Result<Vec<_>>>()
@@ -73,7 +73,7 @@ fn overriden_entries_1() {
 
     let files = MiniFs::new().mount("/f0", f0).mount("/f0", f1);
 
-    let mut entries = files
+    let entries = files
         .entries("/f0")
         .unwrap()
         .collect::<Result<Vec<_>>>()
@@ -97,9 +97,24 @@ fn tuple_no_repeats() {
     assert_eq!(3, entries.len())
 }
 
+#[test]
+fn tuple_no_repeats_nested() {
+    let mut ram = RamFs::new();
+    ram.touch("baz/foobar", vec![]);
+
+    // LocalFs names are relative to its root, RamFs names aren't.
+    let files = (LocalFs::new("./tests/local"), ram);
+    let entries = files
+        .entries("baz")
+        .unwrap()
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+
+    assert_eq!(1, entries.len())
+}
+
 #[test]
 fn local_trait_object_entries() {
-    use mini_fs::prelude::*;
     use mini_fs::{LocalFs, Store};
     use std::path::Path;
 
@@ -154,3 +169,98 @@ fn local_entries() {
         entries.next().unwrap().map(|e| e.name).unwrap()
     );
 }
+
+#[test]
+fn unsupported_entries() {
+    use mini_fs::{File, Store};
+    use std::io::{Error, ErrorKind};
+    use std::path::Path;
+
+    // store that can only open files.
+    struct OpenOnly;
+
+    impl Store for OpenOnly {
+        type File = File;
+
+        fn open_path(&self, _: &Path) -> Result<File> {
+            Err(Error::from(ErrorKind::NotFound))
+        }
+    }
+
+    let files = MiniFs::new().mount("/files", OpenOnly);
+    assert!(files.entries("/files").is_err());
+    assert!(files.entries("/nope").unwrap().next().is_none());
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_entries_not_indexed() {
+    use mini_fs::ZipFs;
+    use std::io::Cursor;
+
+    let file = include_bytes!("archive2.zip");
+    let zip = ZipFs::new(Cursor::new(&file[..]));
+    assert_eq!(2, zip.entries("nested").unwrap().count());
+
+    // the archive is invalid, so it can't be indexed.
+    let files = MiniFs::new().mount("/zip", ZipFs::new(Cursor::new(vec![0u8; 64])));
+    assert!(files.entries("/zip").is_err());
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_entries_not_indexed() {
+    use mini_fs::TarFs;
+    use std::io::Cursor;
+
+    for file in &[
+        &include_bytes!("archive2.tar")[..],
+        &include_bytes!("archive2.tar.gz")[..],
+    ] {
+        let tar = TarFs::new(Cursor::new(*file));
+        assert_eq!(2, tar.entries("nested").unwrap().count());
+        assert_eq!(3, tar.entries(".").unwrap().count());
+    }
+}
+
+#[test]
+fn mini_fs_mount_points() {
+    let mut gfx = RamFs::new();
+    gfx.touch("a.png", vec![]);
+    gfx.touch("ui", vec![]);
+    let mut ui = RamFs::new();
+    ui.touch("b.png", vec![]);
+
+    let files = MiniFs::new()
+        .mount("/assets/gfx", gfx)
+        .mount("/assets/gfx/ui", ui)
+        .mount("/assets/sfx", RamFs::new());
+
+    let entries = |path: &str| {
+        let mut map = BTreeMap::new();
+        for entry in files.entries(path).unwrap() {
+            let entry = entry.unwrap();
+            map.insert(entry.name, entry.kind);
+        }
+        map
+    };
+
+    let root = entries("/");
+    assert_eq!(1, root.len());
+    assert_eq!(Some(&EntryKind::Dir), root.get(OsStr::new("assets")));
+
+    let assets = entries("/assets");
+    assert_eq!(2, assets.len());
+    assert_eq!(Some(&EntryKind::Dir), assets.get(OsStr::new("gfx")));
+    assert_eq!(Some(&EntryKind::Dir), assets.get(OsStr::new("sfx")));
+
+    // the mount point shadows the file of the same name.
+    let gfx = entries("/assets/gfx");
+    assert_eq!(2, gfx.len());
+    assert_eq!(Some(&EntryKind::File), gfx.get(OsStr::new("a.png")));
+    assert_eq!(Some(&EntryKind::Dir), gfx.get(OsStr::new("ui")));
+
+    assert!(files.metadata("/assets").unwrap().is_dir());
+    assert!(files.metadata("/nope").is_err());
+    assert_eq!(2, files.glob("/**/*.png").unwrap().count());
+}
@@ -0,0 +1,132 @@
+use mini_fs::prelude::*;
+use mini_fs::{CaselessFs, MiniFs, RamFs};
+use std::path::PathBuf;
+
+fn glob<S: Store>(store: &S, pattern: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<_> = store.glob(pattern).unwrap().map(|p| p.unwrap()).collect();
+    paths.sort();
+    paths
+}
+
+fn paths(paths: &[&str]) -> Vec<PathBuf> {
+    paths.iter().map(PathBuf::from).collect()
+}
+
+fn ram() -> RamFs {
+    let mut ram = RamFs::new();
+    ram.touch("a.png", vec![]);
+    ram.touch("b.jpg", vec![]);
+    ram.touch("gfx/c.png", vec![]);
+    ram.touch("gfx/ui/d.png", vec![]);
+    ram.touch("gfx/ui/e1.png", vec![]);
+    ram.touch("sfx/f.ogg", vec![]);
+    ram
+}
+
+#[test]
+fn glob_patterns() {
+    let ram = ram();
+
+    assert_eq!(paths(&["a.png"]), glob(&ram, "*.png"));
+    assert_eq!(paths(&["gfx/ui/d.png"]), glob(&ram, "gfx/ui/?.png"));
+    assert_eq!(paths(&["gfx/ui/d.png"]), glob(&ram, "gfx/*/[a-d].png"));
+    assert_eq!(paths(&["gfx/ui/e1.png"]), glob(&ram, "gfx/ui/[!a-d]*"));
+    assert_eq!(
+        paths(&["a.png", "gfx/c.png", "gfx/ui/d.png", "gfx/ui/e1.png"]),
+        glob(&ram, "**/*.png")
+    );
+    assert_eq!(
+        paths(&["gfx/ui/d.png", "gfx/ui/e1.png"]),
+        glob(&ram, "gfx/**/ui/**")
+    );
+    assert_eq!(
+        paths(&["a.png", "b.jpg", "sfx/f.ogg"]),
+        glob(&ram, "{*.{png,jpg},sfx/*}")
+    );
+    assert_eq!(paths(&["sfx/f.ogg"]), glob(&ram, "sfx/f.ogg"));
+    assert!(glob(&ram, "sfx/nope.ogg").is_empty());
+    assert!(glob(&ram, "nope/**/*").is_empty());
+
+    assert!(ram.glob("[a-").is_err());
+    assert!(ram.glob("{a,b").is_err());
+}
+
+#[test]
+fn glob_mini_fs() {
+    let fs = MiniFs::new().mount("/assets", ram());
+    assert_eq!(
+        paths(&["/assets/gfx/ui/d.png", "/assets/gfx/ui/e1.png"]),
+        glob(&fs, "/assets/**/ui/*.png")
+    );
+}
+
+#[test]
+fn glob_caseless() {
+    let fs = CaselessFs::new(ram());
+    let mut paths: Vec<_> = fs
+        .glob("GFX/**/*.PNG")
+        .unwrap()
+        .map(|p| p.unwrap())
+        .collect();
+    paths.sort();
+    assert_eq!(
+        self::paths(&["gfx/c.png", "gfx/ui/d.png", "gfx/ui/e1.png"]),
+        paths
+    );
+    // the case of the pattern matters outside of a CaselessFs.
+    assert!(glob(fs.get_ref(), "GFX/**/*.PNG").is_empty());
+}
+
+#[test]
+fn glob_caseless_mounted() {
+    let fs = MiniFs::new()
+        .mount("/caseless", CaselessFs::new(ram()))
+        .mount("/ram", ram());
+    assert_eq!(
+        paths(&["/caseless/gfx/ui/d.png", "/caseless/gfx/ui/e1.png"]),
+        glob(&fs, "/caseless/GFX/UI/*.PNG")
+    );
+    assert_eq!(paths(&["/caseless/a.png"]), glob(&fs, "/caseless/A.PNG"));
+    assert!(glob(&fs, "/ram/GFX/UI/*.PNG").is_empty());
+    assert!(!fs.case_sensitive("/caseless/gfx"));
+    assert!(fs.case_sensitive("/ram/gfx"));
+
+    let overlay = (CaselessFs::new(RamFs::new()), ram());
+    assert_eq!(paths(&["gfx/c.png"]), glob(&overlay, "GFX/*.PNG"));
+}
+
+#[test]
+fn glob_recursive_counts() {
+    let ram = ram();
+    let count = |pattern| {
+        ram.glob(pattern)
+            .unwrap()
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap()
+            .len()
+    };
+
+    // a.png, b.jpg, gfx, gfx/c.png, gfx/ui, gfx/ui/d.png, gfx/ui/e1.png, sfx
+    // and sfx/f.ogg
+    assert_eq!(9, count("**"));
+    assert_eq!(9, count("**/*"));
+    assert_eq!(9, count("**/**"));
+    assert_eq!(4, count("**/**/*.png"));
+    assert_eq!(2, count("**/ui/**"));
+    assert_eq!(4, count("gfx/**"));
+}
+
+#[test]
+fn glob_through_files() {
+    use mini_fs::LocalFs;
+
+    let local = LocalFs::new("./tests/local");
+    assert!(glob(&local, "foo/*").is_empty());
+    assert!(glob(&local, "foo/**").is_empty());
+    assert!(glob(&local, "foo/bar").is_empty());
+    assert_eq!(paths(&["baz/foobar"]), glob(&local, "*/*"));
+    assert_eq!(
+        paths(&["bar", "baz", "baz/foobar", "foo"]),
+        glob(&local, "**")
+    );
+}
@@ -1,5 +1,6 @@
 use mini_fs::index::*;
 use std::path::Path;
+use std::path::PathBuf;
 
 #[test]
 fn index_insert_get() {
@@ -103,3 +104,64 @@ fn test_normal_path() {
         normalize_path(Path::new("/a/b/c/.././../../"))
     );
 }
+
+#[test]
+fn index_remove() {
+    let mut index = Index::new();
+
+    index.insert("a/b/c.txt", 1);
+    index.insert("a/d.txt", 2);
+    index.insert_dir("e/f");
+
+    assert_eq!(Some(1), index.remove("a/b/c.txt"));
+    assert_eq!(None, index.remove("a/b/c.txt"));
+    assert_eq!(None, index.remove("a"));
+
+    // empty parents are pruned, unless they were inserted explicitly.
+    assert!(!index.is_dir("a/b"));
+    assert!(index.is_dir("a"));
+    assert_eq!(Some(2), index.remove("a/d.txt"));
+    assert!(!index.is_dir("a"));
+
+    index.insert("e/f/g.txt", 3);
+    assert_eq!(Some(3), index.remove("e/f/g.txt"));
+    assert!(index.is_dir("e/f"));
+}
+
+#[test]
+fn index_remove_dir() {
+    let mut index = Index::new();
+
+    index.insert("a/b/c.txt", 1);
+    index.insert("a/b/d/e.txt", 2);
+    index.insert("a/f.txt", 3);
+
+    let mut removed = index.remove_dir("a/b").unwrap();
+    removed.sort();
+    assert_eq!(
+        vec![(PathBuf::from("c.txt"), 1), (PathBuf::from("d/e.txt"), 2)],
+        removed
+    );
+    assert!(!index.is_dir("a/b"));
+    assert!(index.remove_dir("a/b").is_none());
+    assert!(index.remove_dir("a/f.txt").is_none());
+    assert_eq!(Some(&3), index.get("a/f.txt"));
+
+    assert_eq!(1, index.remove_dir("a").unwrap().len());
+    assert_eq!(0, index.entries("").count());
+}
+
+#[test]
+fn index_clear() {
+    let mut index = Index::new();
+
+    index.insert_dir_meta("", 1);
+    index.insert_dir_meta("a", 2);
+    index.insert("a/b.txt", 3);
+
+    index.clear();
+    assert_eq!(None, index.dir_meta(""));
+    assert_eq!(None, index.dir_meta("a"));
+    assert!(!index.is_dir("a"));
+    assert_eq!(0, index.entries("").count());
+}
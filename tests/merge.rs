@@ -53,3 +53,114 @@ fn merge_vec() {
 
     assert_eq!("overriden", atxt);
 }
+
+#[test]
+fn mount_fallthrough() {
+    use mini_fs::prelude::*;
+    use mini_fs::{MiniFs, MountPolicy, RamFs};
+    use std::io::prelude::*;
+
+    let mut base = RamFs::new();
+    let mut mods = RamFs::new();
+
+    base.touch("mods/a.txt", String::from("a.txt").into_bytes());
+    base.touch("mods/b.txt", String::from("b.txt").into_bytes());
+    mods.touch("a.txt", String::from("overriden").into_bytes());
+    mods.touch("c.txt", String::from("c.txt").into_bytes());
+
+    let shadow = MiniFs::new()
+        .mount("/assets", base)
+        .mount("/assets/mods", mods);
+    assert!(shadow.open("/assets/mods/b.txt").is_err());
+    assert_eq!(2, shadow.entries("/assets/mods").unwrap().count());
+
+    let fs = shadow.mount_policy(MountPolicy::Fallthrough);
+    assert!(fs.open("/assets/mods/b.txt").is_ok());
+    assert!(fs.open("/assets/mods/c.txt").is_ok());
+    assert!(fs.metadata("/assets/mods/b.txt").unwrap().is_file());
+    assert!(fs.open("/assets/mods/nope.txt").is_err());
+    assert_eq!(3, fs.entries("/assets/mods").unwrap().count());
+
+    let mut atxt = String::new();
+    let mut file = fs.open("/assets/mods/a.txt").unwrap();
+    file.read_to_string(&mut atxt).unwrap();
+
+    assert_eq!("overriden", atxt);
+}
+
+#[test]
+fn merge_whiteouts() {
+    use mini_fs::prelude::*;
+    use mini_fs::{MiniFs, RamFs};
+
+    let base = || {
+        let mut base = RamFs::new();
+        base.touch("a.txt", vec![]);
+        base.touch("b.txt", vec![]);
+        base.touch("dir/c.txt", vec![]);
+        base.touch("opaque/d.txt", vec![]);
+        base
+    };
+    let mut patch = RamFs::new();
+    patch.whiteout("a.txt");
+    patch.whiteout("dir");
+    patch.touch("opaque/e.txt", vec![]);
+    patch.opaque("opaque");
+
+    let fs = vec![patch, base()];
+    assert!(fs.open("a.txt").is_err());
+    assert!(fs.open("b.txt").is_ok());
+    assert!(fs.open("dir/c.txt").is_err());
+    assert!(fs.metadata("dir").is_err());
+    assert!(fs.open("opaque/d.txt").is_err());
+    assert!(fs.open("opaque/e.txt").is_ok());
+    assert_eq!(2, fs.entries("").unwrap().count());
+    assert_eq!(1, fs.entries("opaque").unwrap().count());
+
+    // whiteouts only hide the files of the lower layers.
+    let mut top = RamFs::new();
+    top.touch("a.txt", vec![]);
+    let fs = MiniFs::new().mount("/files", (top, fs.into_iter().next().unwrap(), base()));
+    assert!(fs.open("/files/a.txt").is_ok());
+    assert!(fs.open("/files/dir/c.txt").is_err());
+    assert_eq!(3, fs.entries("/files").unwrap().count());
+}
+
+#[test]
+fn merge_whiteout_lookups() {
+    use mini_fs::prelude::*;
+    use mini_fs::{Metadata, RamFs};
+    use std::cell::Cell;
+    use std::io;
+    use std::path::Path;
+
+    // counts the lookups of whiteouts.
+    struct Counted(RamFs, Cell<usize>);
+
+    impl Store for Counted {
+        type File = <RamFs as Store>::File;
+
+        fn open_path(&self, path: &Path) -> io::Result<Self::File> {
+            self.0.open_path(path)
+        }
+
+        fn metadata_path(&self, path: &Path) -> io::Result<Metadata> {
+            self.1.set(self.1.get() + 1);
+            self.0.metadata_path(path)
+        }
+    }
+
+    let mut base = RamFs::new();
+    base.touch("deep/a/b/c.txt", vec![]);
+    let mut patch = RamFs::new();
+    patch.whiteout("a.txt");
+
+    let fs = vec![Counted(patch, Cell::new(0)), Counted(base, Cell::new(0))];
+    assert!(fs.open("x/y/z/missing.txt").is_err());
+    assert_eq!(0, fs[0].1.get());
+
+    // the patch doesn't have `deep`, so it only has whiteouts for it at the
+    // root.
+    assert!(fs.open("deep/a/b/c.txt").is_ok());
+    assert_eq!(3, fs[0].1.get());
+}
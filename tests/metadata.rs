@@ -0,0 +1,94 @@
+use mini_fs::prelude::*;
+use mini_fs::{EntryKind, LocalFs, MiniFs, RamFs};
+use std::io::ErrorKind;
+
+#[test]
+fn ram_fs_metadata() {
+    let mut ram = RamFs::new();
+    ram.touch("/a.txt", b"hello".to_vec());
+    ram.touch("/b/c.txt", b"world!".to_vec());
+
+    let meta = ram.metadata("/a.txt").unwrap();
+    assert_eq!(EntryKind::File, meta.kind);
+    assert_eq!(5, meta.len);
+
+    assert!(ram.metadata("/b").unwrap().is_dir());
+    assert_eq!(6, ram.metadata("/b/c.txt").unwrap().len);
+    assert_eq!(
+        ErrorKind::NotFound,
+        ram.metadata("/nope").unwrap_err().kind()
+    );
+}
+
+#[test]
+fn local_fs_metadata() {
+    let local = LocalFs::new("./tests/local");
+
+    let meta = local.metadata("baz").unwrap();
+    assert!(meta.is_dir());
+    assert_eq!(0, meta.len);
+
+    let meta = local.metadata("foo").unwrap();
+    assert!(meta.is_file());
+    assert!(meta.modified.is_some());
+    #[cfg(unix)]
+    assert!(meta.mode.is_some());
+
+    assert!(local.metadata("nope").is_err());
+}
+
+#[test]
+fn mini_fs_metadata() {
+    let mut a = RamFs::new();
+    let mut b = RamFs::new();
+    a.touch("a.txt", b"a".to_vec());
+    b.touch("a.txt", b"overriden".to_vec());
+    b.touch("b.txt", b"b".to_vec());
+
+    let fs = MiniFs::new()
+        .mount("/files", (a, b))
+        .mount("/local", LocalFs::new("./tests/local"));
+
+    assert_eq!(1, fs.metadata("/files/a.txt").unwrap().len);
+    assert_eq!(1, fs.metadata("/files/b.txt").unwrap().len);
+    assert!(fs.metadata("/local/baz").unwrap().is_dir());
+    assert!(fs.metadata("/files/nope").is_err());
+    assert!(fs.metadata("/nope").is_err());
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_metadata() {
+    use mini_fs::ZipFs;
+    use std::io::Cursor;
+
+    let file = include_bytes!("archive2.zip");
+    let zip = ZipFs::new(Cursor::new(&file[..]));
+
+    assert!(zip.metadata("nested").unwrap().is_dir());
+    assert!(zip.metadata(".").unwrap().is_dir());
+
+    let meta = zip.metadata("nested/hello.txt").unwrap();
+    assert!(meta.is_file());
+    assert!(meta.modified.is_some());
+    assert!(zip.metadata("nope").is_err());
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_metadata() {
+    use mini_fs::TarFs;
+    use std::io::Cursor;
+
+    let file = include_bytes!("archive.tar");
+    let tar = TarFs::new(Cursor::new(&file[..]));
+    let meta = tar.metadata("a.txt").unwrap();
+    assert!(meta.is_file());
+    assert_eq!(6, meta.len);
+    assert!(meta.modified.is_some());
+    assert!(tar.metadata("nope").is_err());
+
+    let file = include_bytes!("archive.tar.gz");
+    let tar = TarFs::new(Cursor::new(&file[..]));
+    assert_eq!(7, tar.metadata("b.txt").unwrap().len);
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "mmap")]
+use mini_fs::prelude::*;
+use mini_fs::{File, LocalFs, MiniFs};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+#[test]
+fn local_fs_mmap() {
+    let root = std::env::temp_dir().join(format!("mini-fs-mmap-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.txt"), b"hello world").unwrap();
+    fs::write(root.join("empty.txt"), b"").unwrap();
+
+    let local = LocalFs::new(&root).mmap();
+    let mut file = local.open("a.txt").unwrap();
+    assert_eq!(b"hello world", file.as_bytes());
+    file.seek(SeekFrom::Start(6)).unwrap();
+    let mut content = String::new();
+    file.read_to_string(&mut content).unwrap();
+    assert_eq!("world", content);
+
+    assert!(local.open("empty.txt").unwrap().as_bytes().is_empty());
+    assert!(local.open("nope").is_err());
+    assert_eq!(2, local.entries("").unwrap().count());
+
+    let fs = MiniFs::new().mount("/mmap", local);
+    let file: File = fs.open("/mmap/a.txt").unwrap();
+    assert_eq!(Some(&b"hello world"[..]), file.as_bytes());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_mmap() {
+    use mini_fs::ZipFs;
+    use zip_::write::{SimpleFileOptions, ZipWriter};
+    use zip_::CompressionMethod;
+
+    let path = std::env::temp_dir().join(format!("mini-fs-mmap-{}.zip", std::process::id()));
+    let mut zip = ZipWriter::new(fs::File::create(&path).unwrap());
+    for (name, method) in &[
+        ("stored.txt", CompressionMethod::Stored),
+        ("deflated.txt", CompressionMethod::Deflated),
+        ("stored2.txt", CompressionMethod::Stored),
+    ] {
+        let options = SimpleFileOptions::default().compression_method(*method);
+        zip.start_file(*name, options).unwrap();
+        zip.write_all(name.repeat(16).as_bytes()).unwrap();
+    }
+    zip.finish().unwrap();
+
+    let zip = ZipFs::open_mmap(&path).unwrap();
+    for name in &["stored.txt", "deflated.txt", "stored2.txt"] {
+        let mut file = zip.open(name).unwrap();
+        assert_eq!(Some(name.repeat(16).as_bytes()), file.as_bytes());
+        let mut content = String::new();
+        file.read_to_string(&mut content).unwrap();
+        assert_eq!(name.repeat(16), content);
+    }
+
+    fs::remove_file(&path).unwrap();
+}
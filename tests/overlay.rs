@@ -0,0 +1,113 @@
+use mini_fs::prelude::*;
+use mini_fs::{OpenOptions, OverlayFs, RamFs};
+use std::io::{Read, Write};
+use std::path::Path;
+
+fn read<S: Store>(store: &S, path: &str) -> String
+where
+    S::File: Read,
+{
+    let mut s = String::new();
+    store.open(path).unwrap().read_to_string(&mut s).unwrap();
+    s
+}
+
+fn names<S: Store>(store: &S, path: &str) -> Vec<String> {
+    let mut names: Vec<_> = store
+        .entries(path)
+        .unwrap()
+        .map(|e| {
+            let name = e.unwrap().name;
+            let name = Path::new(&name).file_name().unwrap().to_owned();
+            name.into_string().unwrap()
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+fn overlay() -> OverlayFs<RamFs, RamFs> {
+    let mut lower = RamFs::new();
+    lower.touch("a.txt", b"lower a".to_vec());
+    lower.touch("b.txt", b"lower b".to_vec());
+    lower.touch("dir/c.txt", b"lower c".to_vec());
+    let mut upper = RamFs::new();
+    upper.touch("b.txt", b"upper b".to_vec());
+    OverlayFs::new(lower, upper)
+}
+
+#[test]
+fn overlay_reads() {
+    let fs = overlay();
+    assert_eq!("lower a", read(&fs, "a.txt"));
+    assert_eq!("upper b", read(&fs, "b.txt"));
+    assert!(fs.metadata("dir").unwrap().is_dir());
+    assert_eq!(vec!["a.txt", "b.txt", "dir"], names(&fs, ""));
+    assert!(fs.open("nope.txt").is_err());
+}
+
+#[test]
+fn overlay_copy_up() {
+    let fs = overlay();
+
+    let mut options = OpenOptions::new();
+    options.append(true);
+    fs.open_with("dir/c.txt", &options)
+        .unwrap()
+        .write_all(b" modified")
+        .unwrap();
+    assert_eq!("lower c modified", read(&fs, "dir/c.txt"));
+    assert_eq!("lower c modified", read(fs.upper(), "dir/c.txt"));
+    assert_eq!("lower c", read(fs.lower(), "dir/c.txt"));
+
+    fs.create("dir/d.txt").unwrap().write_all(b"new").unwrap();
+    assert_eq!(vec!["c.txt", "d.txt"], names(&fs, "dir"));
+    assert!(fs.lower().open("dir/d.txt").is_err());
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    assert!(fs.open_with("a.txt", &options).is_err());
+}
+
+#[test]
+fn overlay_remove_rename() {
+    let fs = overlay();
+
+    fs.remove_file("a.txt").unwrap();
+    fs.remove_file("b.txt").unwrap();
+    assert!(fs.open("a.txt").is_err());
+    assert!(fs.open("b.txt").is_err());
+    assert!(fs.lower().open("a.txt").is_ok());
+    assert_eq!(vec!["dir"], names(&fs, ""));
+    assert!(fs.remove_file("a.txt").is_err());
+
+    fs.rename("dir/c.txt", "e.txt").unwrap();
+    assert_eq!("lower c", read(&fs, "e.txt"));
+    assert!(fs.open("dir/c.txt").is_err());
+    assert!(names(&fs, "dir").is_empty());
+
+    // a recreated file replaces the removed one.
+    fs.create("a.txt").unwrap().write_all(b"upper a").unwrap();
+    assert_eq!("upper a", read(&fs, "a.txt"));
+    assert_eq!(vec!["a.txt", "dir", "e.txt"], names(&fs, ""));
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn overlay_tar() {
+    use mini_fs::TarFs;
+
+    let file = include_bytes!("archive2.tar");
+    let tar = TarFs::new(std::io::Cursor::new(&file[..]));
+    let fs = OverlayFs::new(tar, RamFs::new());
+
+    let before = read(&fs, "nested/hello.txt");
+    let mut options = OpenOptions::new();
+    options.append(true);
+    fs.open_with("nested/hello.txt", &options)
+        .unwrap()
+        .write_all(b"!")
+        .unwrap();
+    assert_eq!(format!("{}!", before), read(&fs, "nested/hello.txt"));
+    assert_eq!(vec!["hello.txt", "world.txt"], names(&fs, "nested"));
+}
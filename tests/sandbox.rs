@@ -0,0 +1,66 @@
+use mini_fs::prelude::*;
+use mini_fs::{LocalFs, MiniFs, OpenOptions};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+fn denied<T>(res: std::io::Result<T>) -> bool {
+    res.err().map(|e| e.kind()) == Some(ErrorKind::PermissionDenied)
+}
+
+#[test]
+fn local_fs_sandboxed() {
+    let tmp = std::env::temp_dir().join(format!("mini-fs-sandbox-{}", std::process::id()));
+    let root = tmp.join("root");
+    fs::create_dir_all(root.join("dir")).unwrap();
+    fs::write(root.join("dir/a.txt"), b"a").unwrap();
+    fs::write(tmp.join("secret.txt"), b"secret").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::symlink;
+        symlink(tmp.join("secret.txt"), root.join("link.txt")).unwrap();
+        symlink(&tmp, root.join("link_dir")).unwrap();
+        symlink(tmp.join("nope.txt"), root.join("dangling.txt")).unwrap();
+        symlink(root.join("dir/a.txt"), root.join("inner.txt")).unwrap();
+    }
+
+    let local = LocalFs::sandboxed(&root);
+    assert!(local.open("dir/a.txt").is_ok());
+    assert!(local.open("dir/../dir/a.txt").is_ok());
+    assert_eq!(
+        ErrorKind::NotFound,
+        local.open("dir/nope.txt").unwrap_err().kind()
+    );
+    assert!(denied(local.open(tmp.join("secret.txt"))));
+    assert!(denied(local.open_path(Path::new("../secret.txt"))));
+    assert!(denied(local.open_path(Path::new("dir/../../secret.txt"))));
+    assert!(denied(local.metadata_path(Path::new(".."))));
+    assert!(denied(local.entries_path(Path::new(".."))));
+    // paths are normalized against the root by the convenience methods.
+    assert_eq!(
+        ErrorKind::NotFound,
+        local.open("../secret.txt").unwrap_err().kind()
+    );
+
+    #[cfg(unix)]
+    {
+        assert!(local.open("inner.txt").is_ok());
+        assert!(denied(local.open("link.txt")));
+        assert!(denied(local.open("link_dir/secret.txt")));
+        assert!(denied(local.create("dangling.txt")));
+        assert!(!tmp.join("nope.txt").exists());
+    }
+
+    local.create("dir/b.txt").unwrap().write_all(b"b").unwrap();
+    assert!(denied(local.open_with_path(
+        Path::new("../b.txt"),
+        OpenOptions::new().write(true).create(true)
+    )));
+
+    // mounted stores receive relative paths.
+    let fs = MiniFs::new().mount("/files", LocalFs::sandboxed(&root));
+    assert!(fs.open("/files/dir/a.txt").is_ok());
+    assert!(fs.open("/files/../secret.txt").is_err());
+
+    fs::remove_dir_all(&tmp).unwrap();
+}
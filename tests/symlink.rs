@@ -0,0 +1,150 @@
+use mini_fs::prelude::*;
+use mini_fs::{EntryKind, OpenOptions, RamFs};
+use std::io::Read;
+use std::path::Path;
+
+fn read<S: Store>(store: &S, path: &str) -> String
+where
+    S::File: Read,
+{
+    let mut content = String::new();
+    store
+        .open(path)
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    content
+}
+
+#[test]
+fn ram_fs_symlinks() {
+    let mut ram = RamFs::new();
+    ram.touch("dir/a.txt", &b"hello"[..]);
+    ram.symlink("a.txt", "dir/link.txt");
+    ram.symlink("dir", "link_dir");
+    ram.symlink("../link_dir/link.txt", "dir/nested.txt");
+    ram.symlink("nope.txt", "dangling.txt");
+    ram.symlink("loop_b", "loop_a");
+    ram.symlink("loop_a", "loop_b");
+
+    assert_eq!("hello", read(&ram, "dir/link.txt"));
+    assert_eq!("hello", read(&ram, "link_dir/a.txt"));
+    assert_eq!("hello", read(&ram, "dir/nested.txt"));
+    assert!(ram.metadata("link_dir").unwrap().is_dir());
+    assert_eq!(5, ram.metadata("dir/link.txt").unwrap().len);
+    assert_eq!(Path::new("a.txt"), ram.read_link("dir/link.txt").unwrap());
+    assert!(ram.read_link("dir/a.txt").is_err());
+    assert!(ram.open("dangling.txt").is_err());
+    assert!(ram.open("loop_a").is_err());
+
+    let kinds: Vec<_> = ram
+        .entries("dir")
+        .unwrap()
+        .map(|e| e.unwrap().kind)
+        .collect();
+    assert!(kinds.contains(&EntryKind::Symlink));
+
+    // writing to a link modifies its target.
+    let mut file = ram
+        .open_with(
+            "dir/link.txt",
+            OpenOptions::new().write(true).truncate(true),
+        )
+        .unwrap();
+    std::io::Write::write_all(&mut file, b"world").unwrap();
+    drop(file);
+    assert_eq!("world", read(&ram, "dir/a.txt"));
+
+    // removing a link leaves the target alone.
+    assert!(ram.rm("dir/link.txt").is_none());
+    assert!(ram.open("dir/a.txt").is_ok());
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_symlinks() {
+    use mini_fs::TarFs;
+    use std::io::Cursor;
+    use tar_::{Builder, EntryType, Header};
+
+    let mut builder = Builder::new(Vec::new());
+    let mut header = Header::new_gnu();
+    header.set_size(5);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, "dir/a.txt", &b"hello"[..])
+        .unwrap();
+    for &(kind, path, target) in &[
+        (EntryType::Symlink, "dir/link.txt", "a.txt"),
+        (EntryType::Symlink, "link_dir", "dir"),
+        (EntryType::Symlink, "loop", "loop"),
+        (EntryType::Link, "hard.txt", "dir/a.txt"),
+    ] {
+        let mut header = Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_size(0);
+        builder.append_link(&mut header, path, target).unwrap();
+    }
+    let data = builder.into_inner().unwrap();
+
+    // links are resolved the same way with or without an index.
+    let tar = TarFs::new(Cursor::new(data.clone()));
+    let indexed = TarFs::new(Cursor::new(data)).index().unwrap();
+    for tar in &[tar, indexed] {
+        assert_eq!("hello", read(tar, "dir/link.txt"));
+        assert_eq!("hello", read(tar, "link_dir/a.txt"));
+        assert_eq!("hello", read(tar, "hard.txt"));
+        assert_eq!(5, tar.metadata("link_dir/link.txt").unwrap().len);
+        assert!(tar.metadata("link_dir").unwrap().is_dir());
+        assert!(tar.open("loop").is_err());
+        assert!(tar.open("link_dir/nope.txt").is_err());
+        assert_eq!(Path::new("a.txt"), tar.read_link("dir/link.txt").unwrap());
+        assert_eq!(Path::new("dir"), tar.read_link("link_dir").unwrap());
+    }
+}
+
+#[test]
+#[cfg(unix)]
+fn local_fs_symlinks() {
+    use mini_fs::LocalFs;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    let root = std::env::temp_dir().join(format!("mini-fs-symlink-{}", std::process::id()));
+    fs::create_dir_all(root.join("dir")).unwrap();
+    fs::write(root.join("dir/a.txt"), b"hello").unwrap();
+    symlink("a.txt", root.join("dir/link.txt")).unwrap();
+    symlink("nope.txt", root.join("dir/dangling.txt")).unwrap();
+    symlink("..", root.join("dir/up")).unwrap();
+
+    let kind = |local: &LocalFs, name: &str| {
+        local
+            .entries("dir")
+            .unwrap()
+            .map(|e| e.unwrap())
+            .find(|e| Path::new(&e.name).file_name() == Some(name.as_ref()))
+            .unwrap()
+            .kind
+    };
+
+    let local = LocalFs::new(&root);
+    assert_eq!("hello", read(&local, "dir/link.txt"));
+    assert!(local.metadata("dir/link.txt").unwrap().is_file());
+    assert_eq!(EntryKind::File, kind(&local, "link.txt"));
+    assert_eq!(EntryKind::Symlink, kind(&local, "dangling.txt"));
+    assert_eq!(Path::new("a.txt"), local.read_link("dir/link.txt").unwrap());
+    // links to a parent directory can be followed, but aren't walked into.
+    assert_eq!(EntryKind::Dir, kind(&local, "up"));
+    assert_eq!("hello", read(&local, "dir/up/dir/up/dir/a.txt"));
+    assert_eq!(5, local.walk("").count());
+    assert_eq!(5, local.glob("**").unwrap().count());
+
+    let local = LocalFs::new(&root).follow_links(false);
+    assert!(local.open("dir/link.txt").is_err());
+    assert!(local.open("dir/a.txt").is_ok());
+    assert!(local.metadata("dir/link.txt").unwrap().is_symlink());
+    assert_eq!(EntryKind::Symlink, kind(&local, "link.txt"));
+    assert_eq!(EntryKind::Symlink, kind(&local, "up"));
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -1,11 +1,38 @@
-use std::io::{Cursor, Read};
-use std::path::Path;
+#[cfg(feature = "tar")]
+use std::io::{Cursor, Read, Seek, SeekFrom};
+#[cfg(feature = "tar")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "tar")]
+use std::sync::Arc;
+
+// Counts the bytes read from an archive.
+#[cfg(feature = "tar")]
+struct Counted {
+    inner: Cursor<Vec<u8>>,
+    read: Arc<AtomicUsize>,
+}
+
+#[cfg(feature = "tar")]
+impl Read for Counted {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.fetch_add(n, Ordering::SeqCst);
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "tar")]
+impl Seek for Counted {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
 
 #[test]
 #[cfg(feature = "tar")]
 fn tar() {
     use mini_fs::prelude::*;
-    use mini_fs::{Store, TarFs};
+    use mini_fs::TarFs;
 
     let file = include_bytes!("archive.tar");
     let tar = TarFs::new(Cursor::new(&file[..]));
@@ -48,12 +75,446 @@ fn tar_gz() {
 }
 
 #[test]
-#[ignore] // TODO implement tar index
 #[cfg(feature = "tar")]
 fn tar_entries() {
     use mini_fs::prelude::*;
-    use mini_fs::TarFs;
+    use mini_fs::{EntryKind, TarFs};
 
-    let file = include_bytes!("archive.tar.gz");
+    let file = include_bytes!("archive2.tar");
     let tar = TarFs::new(Cursor::new(&file[..])).index().unwrap();
+
+    assert_eq!(2, tar.entries("nested").unwrap().count());
+    assert_eq!(3, tar.entries(".").unwrap().count());
+
+    let nested = tar
+        .entries(".")
+        .unwrap()
+        .map(Result::unwrap)
+        .find(|e| e.name == "nested")
+        .unwrap();
+    assert_eq!(EntryKind::Dir, nested.kind);
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_gz_entries() {
+    use mini_fs::prelude::*;
+    use mini_fs::{CaselessFs, TarFs};
+
+    let file = include_bytes!("archive2.tar.gz");
+    let tar = TarFs::new(Cursor::new(&file[..])).index().unwrap();
+
+    assert_eq!(2, tar.entries("nested").unwrap().count());
+    assert_eq!(3, tar.entries(".").unwrap().count());
+
+    let caseless = CaselessFs::new(tar);
+    let mut hello = String::new();
+    caseless
+        .open("NESTED/Hello.txt")
+        .unwrap()
+        .read_to_string(&mut hello)
+        .unwrap();
+    assert_eq!("hello\n", hello);
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_indexed_open() {
+    use mini_fs::prelude::*;
+    use mini_fs::TarFs;
+    use std::io::{Seek, SeekFrom};
+
+    for file in &[
+        &include_bytes!("archive2.tar")[..],
+        &include_bytes!("archive2.tar.gz")[..],
+    ] {
+        let tar = TarFs::new(Cursor::new(*file)).index().unwrap();
+
+        // files opened from the same archive don't interfere with each other.
+        let mut hello = tar.open("nested/hello.txt").unwrap();
+        let mut world = tar.open("nested/world.txt").unwrap();
+        let mut buf = [0; 3];
+        hello.read_exact(&mut buf).unwrap();
+        assert_eq!(b"hel", &buf);
+        world.read_exact(&mut buf).unwrap();
+        assert_eq!(b"wor", &buf);
+
+        let mut content = String::new();
+        hello.read_to_string(&mut content).unwrap();
+        assert_eq!("lo\n", content);
+
+        world.seek(SeekFrom::End(-2)).unwrap();
+        let mut content = String::new();
+        world.read_to_string(&mut content).unwrap();
+        assert_eq!("!\n", content);
+
+        assert!(tar.open("nested").is_err());
+        assert!(tar.open("nope").is_err());
+        assert_eq!(6, tar.metadata("hello.txt").unwrap().len);
+        assert!(tar.metadata("nested").unwrap().is_dir());
+    }
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_gz_checkpoints() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use mini_fs::prelude::*;
+    use mini_fs::TarFs;
+
+    // incompressible data, so the archive spans many checkpoints.
+    let mut seed = 42u32;
+    let files: Vec<Vec<u8>> = (0..8)
+        .map(|_| {
+            (0..64 * 1024)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    (seed >> 16) as u8
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut builder = tar_::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (i, data) in files.iter().enumerate() {
+        let mut header = tar_::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, format!("{}.bin", i), &data[..])
+            .unwrap();
+    }
+    let archive = builder.into_inner().unwrap().finish().unwrap();
+
+    let read = Arc::new(AtomicUsize::new(0));
+    let counted = |interval: Option<u64>| {
+        let tar = TarFs::new(Counted {
+            inner: Cursor::new(archive.clone()),
+            read: Arc::clone(&read),
+        });
+        let tar = match interval {
+            Some(interval) => tar.checkpoints(interval),
+            None => tar,
+        };
+        tar.index().unwrap()
+    };
+
+    // without checkpoints, the last member is decompressed from the start.
+    let tar = counted(None);
+    read.store(0, Ordering::SeqCst);
+    let last = files.len() - 1;
+    let mut data = Vec::new();
+    tar.open(format!("{}.bin", last))
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(files[last], data);
+    assert!(read.load(Ordering::SeqCst) > archive.len() * 3 / 4);
+
+    let tar = counted(Some(16 * 1024));
+    for i in (0..files.len()).rev() {
+        read.store(0, Ordering::SeqCst);
+        let mut data = Vec::new();
+        tar.open(format!("{}.bin", i))
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(files[i], data);
+        // the member, plus whatever lies between it and the nearest checkpoint.
+        assert!(read.load(Ordering::SeqCst) < files[i].len() + 64 * 1024);
+    }
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_index_seeks() {
+    use mini_fs::prelude::*;
+    use mini_fs::TarFs;
+
+    let mut builder = tar_::Builder::new(Vec::new());
+    for i in 0..8 {
+        let data = vec![i as u8; 256 * 1024];
+        let mut header = tar_::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, format!("{}.bin", i), &data[..])
+            .unwrap();
+    }
+    let archive = builder.into_inner().unwrap();
+
+    let read = Arc::new(AtomicUsize::new(0));
+    let tar = TarFs::new(Counted {
+        inner: Cursor::new(archive.clone()),
+        read: Arc::clone(&read),
+    })
+    .index()
+    .unwrap();
+    // only the headers are read.
+    assert!(read.load(Ordering::SeqCst) < archive.len() / 8);
+    assert_eq!(8, tar.entries("").unwrap().count());
+
+    let mut data = Vec::new();
+    tar.open("7.bin").unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(vec![7; 256 * 1024], data);
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_pack_minimal_store() {
+    use mini_fs::prelude::*;
+    use mini_fs::tar::{pack_tar, Compression};
+    use mini_fs::{Entries, RamFs, TarFs};
+    use std::io;
+    use std::path::Path;
+
+    // only lists and opens files, without metadata.
+    struct Minimal(RamFs);
+
+    impl Store for Minimal {
+        type File = <RamFs as Store>::File;
+
+        fn open_path(&self, path: &Path) -> io::Result<Self::File> {
+            self.0.open_path(path)
+        }
+
+        fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+            self.0.entries_path(path)
+        }
+    }
+
+    let mut ram = RamFs::new();
+    ram.touch("a.txt", b"hello".to_vec());
+    ram.touch("nested/b.txt", b"world".to_vec());
+    ram.touch("nested/empty.txt", vec![]);
+
+    let archive = pack_tar(&Minimal(ram), "", Vec::new(), Compression::None).unwrap();
+    let tar = TarFs::new(Cursor::new(&archive[..]));
+    let mut world = String::new();
+    tar.open("nested/b.txt")
+        .unwrap()
+        .read_to_string(&mut world)
+        .unwrap();
+    assert_eq!("world", world);
+    assert!(tar.metadata("nested").unwrap().is_dir());
+    assert_eq!(5, tar.metadata("a.txt").unwrap().len);
+    assert_eq!(0, tar.metadata("nested/empty.txt").unwrap().len);
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_gz_trailer() {
+    use mini_fs::TarFs;
+    use std::io::ErrorKind;
+
+    let file = include_bytes!("archive2.tar.gz");
+    // the trailer holds the crc and the size of the decompressed archive.
+    for i in 1..=8 {
+        let mut corrupt = file.to_vec();
+        let len = corrupt.len();
+        corrupt[len - i] ^= 0xff;
+
+        let err = TarFs::new(Cursor::new(&corrupt[..]))
+            .checkpoints(64)
+            .index()
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::InvalidData, err.kind());
+        assert!(TarFs::new(Cursor::new(&corrupt[..])).index().is_err());
+    }
+    assert!(TarFs::new(Cursor::new(&file[..]))
+        .checkpoints(64)
+        .index()
+        .is_ok());
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_pack() {
+    use mini_fs::prelude::*;
+    use mini_fs::tar::{pack_tar, Compression, TarPacker};
+    use mini_fs::{RamFs, TarFs};
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let mut ram = RamFs::new();
+    ram.touch("assets/b.txt", b"world".to_vec());
+    ram.touch("assets/a.txt", b"hello".to_vec());
+    ram.touch("assets/nested/c.txt", vec![1, 2, 3]);
+    ram.symlink("../a.txt", "assets/nested/link.txt");
+    ram.touch("other.txt", vec![]);
+
+    for &compression in &[Compression::None, Compression::Gzip] {
+        let archive = pack_tar(&ram, "assets", Vec::new(), compression).unwrap();
+        let again = pack_tar(&ram, "assets", Vec::new(), compression).unwrap();
+        assert_eq!(archive, again);
+
+        let tar = TarFs::new(Cursor::new(archive));
+        let mut hello = String::new();
+        tar.open("nested/link.txt")
+            .unwrap()
+            .read_to_string(&mut hello)
+            .unwrap();
+        assert_eq!("hello", hello);
+        assert_eq!(
+            Path::new("../a.txt"),
+            tar.read_link("nested/link.txt").unwrap()
+        );
+        assert_eq!(3, tar.entries("").unwrap().count());
+        assert!(tar.metadata("nested").unwrap().is_dir());
+        assert!(tar.open("other.txt").is_err());
+    }
+
+    let time = UNIX_EPOCH + Duration::from_secs(1_556_713_810);
+    let archive = TarPacker::new()
+        .modified(time)
+        .pack(&ram, "assets", Vec::new())
+        .unwrap();
+    let tar = TarFs::new(Cursor::new(&archive[..]));
+    assert_eq!(Some(time), tar.metadata("b.txt").unwrap().modified);
+    assert_eq!(Some(time), tar.metadata("nested").unwrap().modified);
+
+    let tar = TarFs::new(Cursor::new(&archive[..])).index().unwrap();
+    assert_eq!(Some(time), tar.metadata("b.txt").unwrap().modified);
+    let nested = tar.metadata("nested").unwrap();
+    assert!(nested.is_dir());
+    assert_eq!(Some(time), nested.modified);
+    assert!(nested.mode.is_some());
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_compressions() {
+    use mini_fs::prelude::*;
+    use mini_fs::tar::{pack_tar, Compression};
+    use mini_fs::{RamFs, TarFs};
+
+    let mut ram = RamFs::new();
+    ram.touch("a.txt", b"hello".to_vec());
+    ram.touch("nested/b.txt", b"world".to_vec());
+
+    #[allow(unused_mut)]
+    let mut compressions = vec![Compression::None, Compression::Gzip];
+    #[cfg(feature = "zstd")]
+    compressions.push(Compression::Zstd);
+    #[cfg(feature = "xz")]
+    compressions.push(Compression::Xz);
+    #[cfg(feature = "bzip2")]
+    compressions.push(Compression::Bzip2);
+
+    for compression in compressions {
+        let archive = pack_tar(&ram, "", Vec::new(), compression).unwrap();
+        let tar = TarFs::new(Cursor::new(archive));
+        let mut world = String::new();
+        tar.open("nested/b.txt")
+            .unwrap()
+            .read_to_string(&mut world)
+            .unwrap();
+        assert_eq!("world", world);
+        assert_eq!(2, tar.entries("").unwrap().count());
+    }
+}
+
+#[test]
+#[cfg(all(feature = "tar", not(feature = "zstd")))]
+fn tar_unsupported_compression() {
+    use mini_fs::prelude::*;
+    use mini_fs::TarFs;
+    use std::io::ErrorKind;
+
+    let mut zstd = vec![0x28, 0xb5, 0x2f, 0xfd];
+    zstd.resize(1024, 0);
+    let tar = TarFs::new(Cursor::new(zstd));
+    let err = tar.open("a.txt").err().unwrap();
+    assert_eq!(ErrorKind::Unsupported, err.kind());
+    assert!(err.to_string().contains("zstd"));
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_stream() {
+    use mini_fs::prelude::*;
+    use mini_fs::tar::{pack_tar, Compression};
+    use mini_fs::{RamFs, TarFs};
+    use std::io::{Seek, SeekFrom};
+
+    let mut seed = 7u32;
+    let data: Vec<u8> = (0..256 * 1024)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 24) as u8 % 16
+        })
+        .collect();
+    let mut ram = RamFs::new();
+    ram.touch("a.txt", b"hello".to_vec());
+    ram.touch("big.bin", data.clone());
+
+    #[allow(unused_mut)]
+    let mut compressions = vec![Compression::None, Compression::Gzip];
+    #[cfg(feature = "zstd")]
+    compressions.push(Compression::Zstd);
+    #[cfg(feature = "xz")]
+    compressions.push(Compression::Xz);
+
+    for compression in compressions {
+        let archive = pack_tar(&ram, "", Vec::new(), compression).unwrap();
+        for &checkpoints in &[None, Some(8 * 1024)] {
+            let mut tar = TarFs::new(Cursor::new(archive.clone())).stream_files();
+            if let Some(interval) = checkpoints {
+                tar = tar.checkpoints(interval).index().unwrap();
+            }
+            let mut file = tar.open("big.bin").unwrap();
+            assert!(file.as_bytes().is_none());
+
+            let mut buf = [0; 100];
+            file.seek(SeekFrom::Start(200_000)).unwrap();
+            file.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[200_000..200_100], &buf[..]);
+            file.seek(SeekFrom::Current(-50_100)).unwrap();
+            file.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[150_000..150_100], &buf[..]);
+            file.seek(SeekFrom::End(-100)).unwrap();
+            file.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[data.len() - 100..], &buf[..]);
+            assert_eq!(0, file.read(&mut buf).unwrap());
+
+            let mut all = Vec::new();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            file.read_to_end(&mut all).unwrap();
+            assert!(all == data);
+        }
+
+        let tar = TarFs::new(Cursor::new(archive))
+            .stream_files()
+            .buffer_small_files(1024);
+        let small = tar.open("a.txt").unwrap();
+        let big = tar.open("big.bin").unwrap();
+        let compressed = compression != Compression::None;
+        assert_eq!(compressed, small.as_bytes() == Some(&b"hello"[..]));
+        assert!(big.as_bytes().is_none());
+    }
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn tar_borrowed_reader() {
+    use mini_fs::prelude::*;
+    use mini_fs::tar::{pack_tar, Compression};
+    use mini_fs::{RamFs, TarFs};
+
+    let mut ram = RamFs::new();
+    ram.touch("a.txt", b"hello".to_vec());
+
+    for &compression in &[Compression::None, Compression::Gzip] {
+        let archive = pack_tar(&ram, "", Vec::new(), compression).unwrap();
+        let tar = TarFs::new(Cursor::new(&archive[..])).index().unwrap();
+        let mut hello = String::new();
+        let mut file = tar.open("a.txt").unwrap();
+        file.read_to_string(&mut hello).unwrap();
+        assert_eq!("hello", hello);
+        assert_eq!(Some(&b"hello"[..]), file.as_bytes());
+    }
 }
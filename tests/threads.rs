@@ -0,0 +1,89 @@
+use mini_fs::prelude::*;
+use mini_fs::{MiniFs, RamFs};
+use std::io::{Read, Write};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn stores_are_send_sync() {
+    assert_send_sync::<MiniFs>();
+    assert_send_sync::<RamFs>();
+    #[cfg(feature = "zip")]
+    assert_send_sync::<mini_fs::ZipFs<std::fs::File>>();
+    #[cfg(feature = "tar")]
+    assert_send_sync::<mini_fs::TarFs<std::fs::File>>();
+}
+
+#[test]
+fn mini_fs_threads() {
+    let mut ram = RamFs::new();
+    ram.touch("hello.txt", b"hello".to_vec());
+    let fs = MiniFs::new()
+        .mount("/ram", ram)
+        .mount_mut("/rw", RamFs::new());
+    #[cfg(feature = "tar")]
+    let fs = fs.mount(
+        "/tar",
+        mini_fs::TarFs::open("tests/archive2.tar.gz").unwrap(),
+    );
+    #[cfg(feature = "zip")]
+    let fs = fs.mount("/zip", mini_fs::ZipFs::open("tests/archive2.zip").unwrap());
+    let fs = Arc::new(fs);
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let fs = Arc::clone(&fs);
+            thread::spawn(move || {
+                let mut content = String::new();
+                fs.open("/ram/hello.txt")
+                    .unwrap()
+                    .read_to_string(&mut content)
+                    .unwrap();
+                assert_eq!("hello", content);
+
+                #[cfg(feature = "tar")]
+                {
+                    let mut content = String::new();
+                    fs.open("/tar/nested/world.txt")
+                        .unwrap()
+                        .read_to_string(&mut content)
+                        .unwrap();
+                    assert!(content.starts_with("world"));
+                }
+
+                #[cfg(feature = "zip")]
+                {
+                    let mut content = String::new();
+                    fs.open("/zip/nested/hello.txt")
+                        .unwrap()
+                        .read_to_string(&mut content)
+                        .unwrap();
+                    assert!(content.starts_with("hello"));
+                }
+
+                let path = format!("/rw/{}.txt", i);
+                fs.create(&path).unwrap().write_all(b"data").unwrap();
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(8, fs.entries("/rw").unwrap().count());
+}
+
+#[test]
+fn mini_fs_static() {
+    static FS: OnceLock<MiniFs> = OnceLock::new();
+
+    let fs = FS.get_or_init(|| {
+        let mut ram = RamFs::new();
+        ram.touch("a.txt", b"a".to_vec());
+        MiniFs::new().mount("/", ram)
+    });
+    thread::spawn(move || assert!(fs.open("/a.txt").is_ok()))
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,128 @@
+use mini_fs::prelude::*;
+use mini_fs::{EntryKind, LocalFs, RamFs, WalkEntry};
+use std::path::{Path, PathBuf};
+
+fn paths<I: Iterator<Item = std::io::Result<WalkEntry>>>(walk: I) -> Vec<PathBuf> {
+    walk.map(|e| e.unwrap().path).collect()
+}
+
+fn ram() -> RamFs {
+    let mut ram = RamFs::new();
+    ram.touch("a.txt", vec![]);
+    ram.touch("dir/b.txt", vec![]);
+    ram.touch("dir/sub/c.txt", vec![]);
+    ram.touch("other/d.txt", vec![]);
+    ram
+}
+
+#[test]
+fn walk_order() {
+    let ram = ram();
+    let by_path = |a: &WalkEntry, b: &WalkEntry| a.path.cmp(&b.path);
+
+    let depth_first = paths(ram.walk("").sort_by(by_path));
+    let expected: Vec<PathBuf> = vec![
+        "a.txt".into(),
+        "dir".into(),
+        "dir/b.txt".into(),
+        "dir/sub".into(),
+        "dir/sub/c.txt".into(),
+        "other".into(),
+        "other/d.txt".into(),
+    ];
+    assert_eq!(expected, depth_first);
+
+    let breadth_first = paths(ram.walk("").breadth_first().sort_by(by_path));
+    let expected: Vec<PathBuf> = vec![
+        "a.txt".into(),
+        "dir".into(),
+        "other".into(),
+        "dir/b.txt".into(),
+        "dir/sub".into(),
+        "other/d.txt".into(),
+        "dir/sub/c.txt".into(),
+    ];
+    assert_eq!(expected, breadth_first);
+}
+
+#[test]
+fn walk_options() {
+    let ram = ram();
+
+    assert_eq!(3, ram.walk("").max_depth(1).count());
+    assert_eq!(0, ram.walk("").max_depth(0).count());
+    assert_eq!(3, ram.walk("dir").count());
+
+    let pruned = paths(ram.walk("").filter_entry(|e| e.path != Path::new("dir")));
+    assert_eq!(3, pruned.len());
+    assert!(pruned.iter().all(|p| !p.starts_with("dir")));
+
+    let depths: Vec<_> = ram
+        .walk("dir")
+        .map(|e| e.unwrap())
+        .filter(|e| e.kind == EntryKind::File)
+        .map(|e| e.depth)
+        .collect();
+    assert_eq!(2, depths.len());
+    assert!(depths.contains(&1) && depths.contains(&2));
+}
+
+#[test]
+fn walk_local() {
+    let local = LocalFs::new("./tests/local");
+    let mut paths = paths(local.walk(""));
+    paths.sort();
+    let expected: Vec<PathBuf> = vec![
+        "bar".into(),
+        "baz".into(),
+        "baz/foobar".into(),
+        "foo".into(),
+    ];
+    assert_eq!(expected, paths);
+}
+
+#[test]
+#[cfg(unix)]
+fn walk_local_symlink_loop() {
+    use std::fs;
+    use std::os::unix::fs::symlink;
+
+    let root = std::env::temp_dir().join(format!("mini-fs-walk-{}", std::process::id()));
+    fs::create_dir_all(root.join("d")).unwrap();
+    fs::write(root.join("d/a.txt"), b"a").unwrap();
+    symlink("..", root.join("d/up")).unwrap();
+
+    let local = LocalFs::new(&root);
+    let mut entries: Vec<_> = local.walk("").map(|e| e.unwrap()).collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    let kinds: Vec<_> = entries.iter().map(|e| (e.path.clone(), e.kind)).collect();
+    let expected: Vec<(PathBuf, EntryKind)> = vec![
+        ("d".into(), EntryKind::Dir),
+        ("d/a.txt".into(), EntryKind::File),
+        ("d/up".into(), EntryKind::Symlink),
+    ];
+    assert_eq!(expected, kinds);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+#[cfg(feature = "tar")]
+fn walk_mini_fs() {
+    use mini_fs::{MiniFs, TarFs};
+
+    let file = include_bytes!("archive2.tar");
+    let tar = TarFs::new(std::io::Cursor::new(&file[..]));
+    let fs = MiniFs::new().mount("/tar", tar);
+
+    let mut paths = paths(fs.walk("/tar"));
+    paths.sort();
+    let expected: Vec<PathBuf> = vec![
+        "/tar/hello.txt".into(),
+        "/tar/nested".into(),
+        "/tar/nested/hello.txt".into(),
+        "/tar/nested/world.txt".into(),
+        "/tar/world.txt".into(),
+    ];
+    assert_eq!(expected, paths);
+}
@@ -0,0 +1,168 @@
+use mini_fs::prelude::*;
+use mini_fs::{EntryKind, LocalFs, MiniFs, OpenOptions, RamFs};
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+
+fn read_to_string<S: Store>(store: &S, path: &str) -> String
+where
+    S::File: Read,
+{
+    let mut s = String::new();
+    store.open(path).unwrap().read_to_string(&mut s).unwrap();
+    s
+}
+
+#[test]
+fn ram_fs_write() {
+    let ram = RamFs::new();
+
+    let mut file = ram.create("/a.txt").unwrap();
+    file.write_all(b"hello").unwrap();
+    drop(file);
+    assert_eq!("hello", read_to_string(&ram, "/a.txt"));
+
+    let mut options = OpenOptions::new();
+    options.append(true);
+    let mut file = ram.open_with("/a.txt", &options).unwrap();
+    file.write_all(b" world").unwrap();
+    file.flush().unwrap();
+    assert_eq!("hello world", read_to_string(&ram, "/a.txt"));
+
+    // truncated on open
+    drop(ram.create("/a.txt").unwrap());
+    assert_eq!("", read_to_string(&ram, "/a.txt"));
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    let err = ram.open_with("/a.txt", &options).err().unwrap();
+    assert_eq!(ErrorKind::AlreadyExists, err.kind());
+
+    // files opened for reading can't be written to
+    let mut file = ram.open("/a.txt").unwrap();
+    assert!(file.write_all(b"nope").is_err());
+}
+
+#[test]
+fn ram_fs_remove_rename() {
+    let ram = RamFs::new();
+    ram.create_dir("/dir").unwrap();
+    ram.create("/dir/a.txt").unwrap().write_all(b"a").unwrap();
+
+    let entries = ram.entries("/").unwrap().collect::<Vec<_>>();
+    assert_eq!(1, entries.len());
+    assert_eq!(EntryKind::Dir, entries[0].as_ref().unwrap().kind);
+    assert_eq!(
+        ErrorKind::AlreadyExists,
+        ram.create_dir("/dir").unwrap_err().kind()
+    );
+
+    ram.rename("/dir/a.txt", "/dir/b.txt").unwrap();
+    assert!(ram.open("/dir/a.txt").is_err());
+    assert_eq!("a", read_to_string(&ram, "/dir/b.txt"));
+
+    ram.remove_file("/dir/b.txt").unwrap();
+    assert!(ram.open("/dir/b.txt").is_err());
+    assert_eq!(
+        ErrorKind::NotFound,
+        ram.remove_file("/dir/b.txt").unwrap_err().kind()
+    );
+
+    // removing or renaming a file that is open for writing doesn't bring it
+    // back when the file is closed.
+    let mut file = ram.create("/dir/c.txt").unwrap();
+    file.write_all(b"c").unwrap();
+    ram.remove_file("/dir/c.txt").unwrap();
+    drop(file);
+    assert!(ram.open("/dir/c.txt").is_err());
+
+    let mut file = ram.create("/dir/d.txt").unwrap();
+    ram.rename("/dir/d.txt", "/dir/e.txt").unwrap();
+    file.write_all(b"d").unwrap();
+    file.flush().unwrap();
+    assert!(ram.open("/dir/d.txt").is_err());
+    assert!(ram.open("/dir/e.txt").is_ok());
+}
+
+#[test]
+fn local_fs_write() {
+    let root = std::env::temp_dir().join(format!("mini-fs-write-{}", std::process::id()));
+    fs::create_dir_all(&root).unwrap();
+    let local = LocalFs::new(&root);
+
+    local.create_dir("dir").unwrap();
+    local
+        .create("dir/a.txt")
+        .unwrap()
+        .write_all(b"hello")
+        .unwrap();
+    assert_eq!("hello", read_to_string(&local, "dir/a.txt"));
+
+    local.rename("dir/a.txt", "dir/b.txt").unwrap();
+    assert!(local.open("dir/a.txt").is_err());
+    local.remove_file("dir/b.txt").unwrap();
+    assert!(local.open("dir/b.txt").is_err());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn mini_fs_write() {
+    let fs = MiniFs::new()
+        .mount("/ro", RamFs::new())
+        .mount_mut("/rw", RamFs::new());
+
+    fs.create("/rw/a.txt").unwrap().write_all(b"hello").unwrap();
+    fs.rename("/rw/a.txt", "/rw/b.txt").unwrap();
+    assert_eq!("hello", read_to_string(&fs, "/rw/b.txt"));
+
+    let err = fs.create("/ro/a.txt").err().unwrap();
+    assert_eq!(ErrorKind::PermissionDenied, err.kind());
+    assert!(fs.rename("/rw/b.txt", "/ro/b.txt").is_err());
+    assert!(fs.create("/nope/a.txt").is_err());
+
+    fs.remove_file("/rw/b.txt").unwrap();
+    assert!(fs.open("/rw/b.txt").is_err());
+}
+
+#[test]
+fn mini_fs_rename_fallthrough() {
+    use mini_fs::MountPolicy;
+
+    let mut base = RamFs::new();
+    base.touch("mods/a.txt", b"a".to_vec());
+    let mut mods = RamFs::new();
+    mods.touch("b.txt", b"b".to_vec());
+
+    let fs = MiniFs::new()
+        .mount_mut("/assets", base)
+        .mount_mut("/assets/mods", mods);
+    let err = fs.rename("/assets/mods/a.txt", "/assets/mods/c.txt");
+    assert_eq!(ErrorKind::NotFound, err.unwrap_err().kind());
+
+    let fs = fs.mount_policy(MountPolicy::Fallthrough);
+    fs.rename("/assets/mods/a.txt", "/assets/mods/c.txt")
+        .unwrap();
+    assert_eq!("a", read_to_string(&fs, "/assets/mods/c.txt"));
+    assert!(fs.open("/assets/mods/a.txt").is_err());
+    fs.rename("/assets/mods/b.txt", "/assets/mods/d.txt")
+        .unwrap();
+    assert_eq!("b", read_to_string(&fs, "/assets/mods/d.txt"));
+    assert!(fs.rename("/assets/mods/nope.txt", "/assets/e.txt").is_err());
+}
+
+#[test]
+fn ram_fs_rm() {
+    let mut ram = RamFs::new();
+    ram.touch("/a/b.txt", b"b".to_vec());
+    ram.touch("/a/c/d.txt", b"d".to_vec());
+    ram.touch("/e.txt", b"e".to_vec());
+
+    assert_eq!(&b"e"[..], &*ram.rm("/e.txt").unwrap());
+    assert!(ram.rm("/e.txt").is_none());
+    assert!(ram.open("/e.txt").is_err());
+
+    assert_eq!(2, ram.rm_dir("/a").unwrap().len());
+    assert!(ram.rm_dir("/a").is_none());
+    assert!(ram.metadata("/a").is_err());
+    assert!(ram.open("/a/b.txt").is_err());
+}
@@ -1,5 +1,5 @@
+#[cfg(feature = "zip")]
 use std::io::{Cursor, Read};
-use std::path::Path;
 
 #[test]
 #[cfg(feature = "zip")]
@@ -34,3 +34,381 @@ fn zip_entries() {
     assert_eq!(2, zip.entries("nested").unwrap().collect::<Vec<_>>().len());
     assert_eq!(3, zip.entries(".").unwrap().collect::<Vec<_>>().len());
 }
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_pack() {
+    use mini_fs::prelude::*;
+    use mini_fs::zip::{pack_zip, CompressionMethod, ZipPacker};
+    use mini_fs::{EntryKind, RamFs, ZipFs};
+    use std::ffi::OsString;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let mut ram = RamFs::new();
+    ram.touch("assets/b.txt", b"world".to_vec());
+    ram.touch("assets/a.txt", b"hello".to_vec());
+    ram.touch("assets/nested/c.png", vec![1, 2, 3]);
+    ram.touch("other.txt", vec![]);
+    ram.create_dir("assets/empty").unwrap();
+
+    let archive = pack_zip(&ram, "assets", Cursor::new(Vec::new()))
+        .unwrap()
+        .into_inner();
+    let again = pack_zip(&ram, "assets", Cursor::new(Vec::new()))
+        .unwrap()
+        .into_inner();
+    assert_eq!(archive, again);
+
+    let zip = ZipFs::new(Cursor::new(&archive[..])).index().unwrap();
+    let mut hello = String::new();
+    zip.open("a.txt")
+        .unwrap()
+        .read_to_string(&mut hello)
+        .unwrap();
+    assert_eq!("hello", hello);
+    let mut entries: Vec<_> = zip
+        .entries("")
+        .unwrap()
+        .map(|e| e.unwrap())
+        .map(|e| (e.name, e.kind))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let expected: Vec<(OsString, EntryKind)> = vec![
+        ("a.txt".into(), EntryKind::File),
+        ("b.txt".into(), EntryKind::File),
+        ("empty".into(), EntryKind::Dir),
+        ("nested".into(), EntryKind::Dir),
+    ];
+    assert_eq!(expected, entries);
+    assert_eq!(0, zip.entries("empty").unwrap().count());
+    assert!(zip.metadata("empty").unwrap().is_dir());
+    assert!(zip.metadata("nested").unwrap().is_dir());
+    assert!(zip.open("other.txt").is_err());
+
+    // 2019-05-01 12:30:10 UTC
+    let time = UNIX_EPOCH + Duration::from_secs(1_556_713_810);
+    let archive = ZipPacker::new()
+        .modified(time)
+        .compression_method_by(|path| match path.extension() {
+            Some(ext) if ext == "png" => CompressionMethod::Stored,
+            _ => CompressionMethod::Deflated,
+        })
+        .pack(&ram, "assets", Cursor::new(Vec::new()))
+        .unwrap();
+    let mut archive = zip_::ZipArchive::new(archive).unwrap();
+    let names: Vec<_> = (0..archive.len())
+        .map(|i| archive.by_index(i).unwrap().name().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        vec!["a.txt", "b.txt", "empty/", "nested/", "nested/c.png"],
+        names
+    );
+    let png = archive.by_name("nested/c.png").unwrap();
+    assert_eq!(zip_::CompressionMethod::Stored, png.compression());
+    let modified = png.last_modified().unwrap();
+    assert_eq!(
+        (2019, 5, 1),
+        (modified.year(), modified.month(), modified.day())
+    );
+    assert_eq!(
+        (12, 30, 10),
+        (modified.hour(), modified.minute(), modified.second())
+    );
+    drop(png);
+    let txt = archive.by_name("a.txt").unwrap();
+    assert_eq!(zip_::CompressionMethod::Deflated, txt.compression());
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_methods() {
+    use mini_fs::prelude::*;
+    use mini_fs::ZipFs;
+    use std::io::ErrorKind;
+
+    let file = include_bytes!("methods.zip");
+    let zip = ZipFs::new(Cursor::new(&file[..]));
+
+    let read = |path: &str| {
+        let mut content = String::new();
+        zip.open(path)?.read_to_string(&mut content)?;
+        Ok::<_, std::io::Error>(content)
+    };
+
+    // sizes stored in the zip64 extra field of both headers.
+    let mut writer = zip_::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip_::write::SimpleFileOptions::default().large_file(true);
+    writer.start_file("zip64.txt", options).unwrap();
+    std::io::Write::write_all(&mut writer, b"zip64\n").unwrap();
+    let archive = writer.finish().unwrap().into_inner();
+    let central = archive.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+    assert_eq!([0xff; 8], archive[central + 20..central + 28]);
+    let zip64 = ZipFs::new(Cursor::new(&archive[..]));
+    let mut content = String::new();
+    zip64
+        .open("zip64.txt")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!("zip64\n", content);
+
+    let file = include_bytes!("deflate64.zip");
+    let deflate64 = ZipFs::new(Cursor::new(&file[..]));
+    let mut content = String::new();
+    deflate64
+        .open("deflate64.txt")
+        .unwrap()
+        .read_to_string(&mut content)
+        .unwrap();
+    assert_eq!("deflate64\n".repeat(4), content);
+
+    for &(path, feature, enabled) in &[
+        ("bzip2.txt", "bzip2", cfg!(feature = "bzip2")),
+        ("lzma.txt", "lzma", cfg!(feature = "lzma")),
+    ] {
+        match read(path) {
+            Ok(content) => {
+                assert!(enabled);
+                assert_eq!(format!("{}\n", feature), content);
+            }
+            Err(err) => {
+                assert!(!enabled, "{}: {}", path, err);
+                assert_eq!(ErrorKind::Unsupported, err.kind());
+                assert!(err.to_string().contains(feature));
+            }
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    {
+        use mini_fs::zip::{CompressionMethod, ZipPacker};
+        use mini_fs::RamFs;
+
+        let mut ram = RamFs::new();
+        ram.touch("zstd.txt", b"zstd\n".to_vec());
+        let archive = ZipPacker::new()
+            .compression_method(CompressionMethod::Zstd)
+            .pack(&ram, "", Cursor::new(Vec::new()))
+            .unwrap();
+        let zip = ZipFs::new(archive);
+        let mut content = String::new();
+        zip.open("zstd.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!("zstd\n", content);
+    }
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_password() {
+    use mini_fs::prelude::*;
+    use mini_fs::ZipFs;
+    use std::io::{ErrorKind, Write};
+    use std::path::Path;
+    use zip_::unstable::write::FileOptionsExt;
+    use zip_::write::{SimpleFileOptions, ZipWriter};
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.start_file("plain.txt", options).unwrap();
+    zip.write_all(b"plain").unwrap();
+    let encrypted = options.with_deprecated_encryption(b"secret").unwrap();
+    zip.start_file("secret.txt", encrypted).unwrap();
+    zip.write_all(b"secret").unwrap();
+    let other = options.with_deprecated_encryption(b"other").unwrap();
+    zip.start_file("other.txt", other).unwrap();
+    zip.write_all(b"other").unwrap();
+    #[cfg(feature = "aes")]
+    {
+        let aes = options.with_aes_encryption(zip_::AesMode::Aes256, "secret");
+        zip.start_file("aes.txt", aes).unwrap();
+        zip.write_all(b"aes").unwrap();
+    }
+    let archive = zip.finish().unwrap().into_inner();
+
+    let read = |zip: &ZipFs<_>, path: &str| {
+        let mut content = String::new();
+        zip.open(path)?.read_to_string(&mut content)?;
+        Ok::<_, std::io::Error>(content)
+    };
+
+    let zip = ZipFs::new(Cursor::new(archive.clone()));
+    assert_eq!("plain", read(&zip, "plain.txt").unwrap());
+    let err = read(&zip, "secret.txt").unwrap_err();
+    assert_eq!(ErrorKind::PermissionDenied, err.kind());
+
+    let zip = ZipFs::new(Cursor::new(archive.clone())).with_password("secret");
+    assert_eq!("plain", read(&zip, "plain.txt").unwrap());
+    assert_eq!("secret", read(&zip, "secret.txt").unwrap());
+    let err = read(&zip, "other.txt").unwrap_err();
+    assert_eq!(ErrorKind::PermissionDenied, err.kind());
+    #[cfg(feature = "aes")]
+    assert_eq!("aes", read(&zip, "aes.txt").unwrap());
+
+    let zip = ZipFs::new(Cursor::new(archive)).with_password_fn(|path| {
+        if path == Path::new("other.txt") {
+            Some(b"other".to_vec())
+        } else {
+            Some(b"secret".to_vec())
+        }
+    });
+    assert_eq!("secret", read(&zip, "secret.txt").unwrap());
+    assert_eq!("other", read(&zip, "other.txt").unwrap());
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_cached_archive() {
+    use mini_fs::prelude::*;
+    use mini_fs::ZipFs;
+    use std::io::{Seek, SeekFrom};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // counts the seeks from the end, which locate the central directory.
+    struct Reader(Cursor<&'static [u8]>, Arc<AtomicUsize>);
+
+    impl Read for Reader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Seek for Reader {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            if let SeekFrom::End(_) = pos {
+                self.1.fetch_add(1, Ordering::SeqCst);
+            }
+            self.0.seek(pos)
+        }
+    }
+
+    let seeks = Arc::new(AtomicUsize::new(0));
+    let file = include_bytes!("archive2.zip");
+    let zip = ZipFs::new(Reader(Cursor::new(&file[..]), Arc::clone(&seeks)));
+
+    assert!(zip.metadata("nested").unwrap().is_dir());
+    let parsed = seeks.load(Ordering::SeqCst);
+    assert!(parsed > 0);
+    for _ in 0..4 {
+        let mut hello = String::new();
+        zip.open("nested/hello.txt")
+            .unwrap()
+            .read_to_string(&mut hello)
+            .unwrap();
+        assert!(zip.metadata("nested/world.txt").unwrap().is_file());
+        assert!(zip.open("nope.txt").is_err());
+    }
+    assert_eq!(3, zip.entries("").unwrap().count());
+    assert_eq!(parsed, seeks.load(Ordering::SeqCst));
+
+    // an invalid archive keeps failing.
+    let zip = ZipFs::new(Cursor::new(&b"not a zip"[..]));
+    assert!(zip.open("hello.txt").is_err());
+    assert!(zip.open("hello.txt").is_err());
+    assert!(zip.entries("").is_err());
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_pack_minimal_store() {
+    use mini_fs::prelude::*;
+    use mini_fs::zip::pack_zip;
+    use mini_fs::{Entries, RamFs, ZipFs};
+    use std::io;
+    use std::path::Path;
+
+    // only lists and opens files, without metadata.
+    struct Minimal(RamFs);
+
+    impl Store for Minimal {
+        type File = <RamFs as Store>::File;
+
+        fn open_path(&self, path: &Path) -> io::Result<Self::File> {
+            self.0.open_path(path)
+        }
+
+        fn entries_path(&self, path: &Path) -> io::Result<Entries<'_>> {
+            self.0.entries_path(path)
+        }
+    }
+
+    let mut ram = RamFs::new();
+    ram.touch("a.txt", b"hello".to_vec());
+    ram.touch("nested/b.txt", b"world".to_vec());
+
+    let archive = pack_zip(&Minimal(ram), "", Cursor::new(Vec::new()))
+        .unwrap()
+        .into_inner();
+    let zip = ZipFs::new(Cursor::new(&archive[..]));
+    let mut world = String::new();
+    zip.open("nested/b.txt")
+        .unwrap()
+        .read_to_string(&mut world)
+        .unwrap();
+    assert_eq!("world", world);
+    assert!(zip.metadata("nested").unwrap().is_dir());
+    assert_eq!(5, zip.metadata("a.txt").unwrap().len);
+}
+
+#[test]
+#[cfg(feature = "zip")]
+fn zip_stream() {
+    use mini_fs::prelude::*;
+    use mini_fs::zip::{CompressionMethod, ZipPacker};
+    use mini_fs::{RamFs, ZipFs};
+    use std::io::{Seek, SeekFrom};
+
+    let mut seed = 7u32;
+    let data: Vec<u8> = (0..256 * 1024)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 24) as u8 % 16
+        })
+        .collect();
+    let mut ram = RamFs::new();
+    ram.touch("a.txt", b"hello".to_vec());
+    ram.touch("big.bin", data.clone());
+
+    for &method in &[CompressionMethod::Stored, CompressionMethod::Deflated] {
+        let archive = ZipPacker::new()
+            .compression_method(method)
+            .pack(&ram, "", Cursor::new(Vec::new()))
+            .unwrap()
+            .into_inner();
+
+        let zip = ZipFs::new(Cursor::new(archive.clone())).stream_files();
+        let mut file = zip.open("big.bin").unwrap();
+        assert!(file.as_bytes().is_none());
+
+        let mut buf = [0; 100];
+        file.seek(SeekFrom::Start(200_000)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&data[200_000..200_100], &buf[..]);
+        file.seek(SeekFrom::Current(-50_100)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&data[150_000..150_100], &buf[..]);
+        file.seek(SeekFrom::End(-100)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&data[data.len() - 100..], &buf[..]);
+        assert_eq!(0, file.read(&mut buf).unwrap());
+
+        // files are read independently of each other.
+        let mut other = zip.open("big.bin").unwrap();
+        let mut all = Vec::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        other.read_to_end(&mut all).unwrap();
+        assert_eq!(&data[..100], &buf[..]);
+        assert!(all == data);
+
+        let zip = ZipFs::new(Cursor::new(archive))
+            .stream_files()
+            .buffer_small_files(1024);
+        let small = zip.open("a.txt").unwrap();
+        assert_eq!(Some(&b"hello"[..]), small.as_bytes());
+        assert!(zip.open("big.bin").unwrap().as_bytes().is_none());
+    }
+}